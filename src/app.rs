@@ -1,38 +1,328 @@
-use std::env::args;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::{Duration, Instant};
 use anyhow::Result;
-use ratatui::layout::Rect;
-use crate::tui::Tui;
-use crate::timer::Timer;
-use crate::util::get_prev_whitespace;
+use ratatui::layout::{Position, Rect};
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+use crate::clock;
+use crate::timer::{Countdown, Timer};
+use crate::race::{Race, RaceEvent, RACE_COUNTDOWN};
+use crate::input::Input;
+use crate::keyboard::{Keyboard, Layout, WRONG_KEY_FLASH};
+use crate::keymap::Keymap;
+use crate::config::Config;
+use crate::palette::Palette;
+use crate::home::Home;
+use crate::history::{get_unix_time, Flag, History, HistoryView, TestResult};
+use crate::ngrams::Ngrams;
+use crate::util::{get_arg, get_prev_whitespace, QuoteLength};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ErrorMode {
+    /// mistakes are only counted
+    Normal,
+    /// a wrong key is rejected and the cursor doesn't advance
+    StopOnLetter,
+    /// a word with mistakes can't be left with space
+    StopOnWord,
+    /// any mistake restarts the test with the same text
+    SuddenDeathRetry,
+    /// any mistake restarts the test with a new text
+    SuddenDeath,
+}
+
+impl ErrorMode {
+    pub const ALL: [ErrorMode; 5] = [
+        ErrorMode::Normal,
+        ErrorMode::StopOnLetter,
+        ErrorMode::StopOnWord,
+        ErrorMode::SuddenDeathRetry,
+        ErrorMode::SuddenDeath,
+    ];
+
+    pub fn new(args: &[String]) -> Result<Self> {
+        if args.contains(&"-d".to_string()) {
+            return Ok(ErrorMode::SuddenDeath);
+        }
+
+        let Some(idx) = args.iter().position(|i| i == "-e") else {
+            return Ok(ErrorMode::Normal);
+        };
+
+        match args.get(idx + 1).map(String::as_str) {
+            Some("letter") => Ok(ErrorMode::StopOnLetter),
+            Some("word") => Ok(ErrorMode::StopOnWord),
+            Some("retry") => Ok(ErrorMode::SuddenDeathRetry),
+            Some("death") => Ok(ErrorMode::SuddenDeath),
+            _ => anyhow::bail!("add mode after -e: letter, word, retry or death (e.g: -e word)"),
+        }
+    }
+}
+
+impl fmt::Display for ErrorMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ErrorMode::Normal => "normal",
+            ErrorMode::StopOnLetter => "stop on letter",
+            ErrorMode::StopOnWord => "stop on word",
+            ErrorMode::SuddenDeathRetry => "sudden death (retry)",
+            ErrorMode::SuddenDeath => "sudden death",
+        })
+    }
+}
+
+/// what the text of the next test is made of
+pub struct TextSource {
+    pub word_count: usize,
+    pub quotes: bool,
+    /// any length if None
+    pub quote_length: Option<QuoteLength>,
+    /// a word list in ~/.config/tt-rs/lists, words.ron is used without one
+    pub list: Option<String>,
+}
+
+impl TextSource {
+    pub fn new(args: &[String]) -> Result<Self> {
+        Ok(Self {
+            word_count: get_arg(args, "-w", "30")?.unwrap_or(25),
+            quotes: args.contains(&"-q".to_string()),
+            quote_length: None,
+            list: get_arg(args, "--list", "german")?,
+        })
+    }
+}
+
+/// how many letters can be typed past the end of a word
+const MAX_EXTRA_CHARS: usize = 10;
+
+/// how many times each missed word is typed when practicing them
+const MISSED_WORD_REPEATS: usize = 3;
+
+/// a longer time between keys is a break, not a slow transition
+const MAX_NGRAM_LATENCY: Duration = Duration::from_secs(2);
+
+/// faster than anyone has typed for a whole test
+const MAX_WPM: f64 = 300.0;
+/// people vary the time between keys by far more than 10% of it
+const MIN_KEYSTROKE_VARIATION: f64 = 0.1;
+/// too few keys say nothing about how they were typed
+const MIN_KEYSTROKES: usize = 20;
+
+pub struct FailConditions {
+    min_accuracy: Option<f64>,
+    min_wpm: Option<f64>,
+    /// nothing can fail before this much time has passed
+    grace: Duration,
+}
+
+impl FailConditions {
+    pub fn new(args: &[String]) -> Result<Self> {
+        Ok(Self {
+            min_accuracy: get_arg(args, "--min-acc", "95")?,
+            min_wpm: get_arg(args, "--min-wpm", "60")?,
+            grace: Duration::from_secs(get_arg(args, "--grace", "5")?.unwrap_or(5)),
+        })
+    }
+}
+
+pub enum FailReason {
+    Accuracy(f64),
+    Speed(f64),
+}
+
+impl fmt::Display for FailReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FailReason::Accuracy(min) => write!(f, "accuracy dropped below {}%", min),
+            FailReason::Speed(min) => write!(f, "speed dropped below {} WPM", min),
+        }
+    }
+}
+
+/// clickable under the results
+#[derive(Clone, Copy, PartialEq)]
+pub enum Button {
+    Next,
+    Retry,
+    /// only when some words were missed
+    PracticeMissed,
+    History,
+}
+
+impl Button {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Button::Next => "next",
+            Button::Retry => "retry",
+            Button::PracticeMissed => "practice missed",
+            Button::History => "history",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LiveStatsPosition {
+    Top,
+    Bottom,
+}
+
+impl LiveStatsPosition {
+    pub fn new(args: &[String]) -> Result<Option<Self>> {
+        let Some(idx) = args.iter().position(|i| i == "--live") else {
+            return Ok(None);
+        };
+
+        match args.get(idx + 1).map(String::as_str) {
+            Some("top") => Ok(Some(LiveStatsPosition::Top)),
+            Some("bottom") => Ok(Some(LiveStatsPosition::Bottom)),
+            _ => anyhow::bail!("add position after --live: top or bottom (e.g: --live top)"),
+        }
+    }
+}
 
 pub struct App {
     exit: bool,
-    pub target_text: Vec<char>,
-    pub curr_text: Vec<char>,
+    /// one grapheme cluster per element
+    pub target_text: Vec<String>,
+    pub curr_text: Vec<String>,
+    /// letters typed past the end of a word, keyed by the index
+    /// of the whitespace (or the end of the text) they were typed at
+    pub extra_text: BTreeMap<usize, Vec<String>>,
     rect: Rect,
     scroller: bool,
     pub timer: Timer,
+    pub countdown: Option<Countdown>,
+    pub race: Option<Race>,
+    pub error_mode: ErrorMode,
+    pub input: Input,
+    pub keymap: Keymap,
+    pub text_source: TextSource,
+    pub palette: Option<Palette>,
+    pub home: Option<Home>,
+    pub history: History,
+    pub history_view: Option<HistoryView>,
+    pub ngrams: Ngrams,
+    /// the slowest bigrams and trigrams are shown instead of the test
+    pub show_ngrams: bool,
+    /// the text starts from a clicked word, results aren't saved
+    practice: bool,
+    /// the result of the current test is in the history
+    recorded: bool,
+    /// the timer was running on the last tick
+    running: bool,
+    /// every test finished since the start, for --json and --csv
+    pub session_results: Vec<TestResult>,
+    /// the best result of this kind of test before the current one
+    pub previous_best: Option<TestResult>,
+    /// flags raised by events during the test (pasting, losing focus)
+    flags: Vec<Flag>,
+    /// when each key was typed (in test time, so pauses are left out) and where the cursor was after it
+    keystrokes: Vec<(Duration, usize)>,
+    /// where each wrong letter was typed, extra letters at the end of their word
+    mistakes: Vec<usize>,
+    /// when each letter was last typed right
+    hits: BTreeMap<usize, Instant>,
+    /// the layout being practiced, keys are remapped from qwerty to it
+    pub layout: Option<Layout>,
+    show_keyboard: bool,
+    keyboard_rect: Option<Rect>,
+    buttons: Vec<(Button, Rect)>,
+    wrong_key: Option<(char, Instant)>,
+    fail_conditions: FailConditions,
+    pub failed: Option<FailReason>,
+    live_stats: bool,
+    live_stats_position: LiveStatsPosition,
     correct_chars: u32,
     incorrect_chars: u32,
+    /// a letter that can still become the target with a combining mark typed after it
+    pending_letter: Option<usize>,
+    /// the last letter counted and if it was right, it's counted again once a mark is combined with it
+    last_counted: Option<(usize, bool)>,
 }
 
 impl App {
-    pub fn new(args: &Vec<String>) -> Result<(Self, Tui)> {
-        let target_text = App::gen_target_text(&args)?;
-
-        Ok((
-            Self {
-                exit: false,
-                scroller: false,
-                timer: Timer::new(&args)?,
-                correct_chars: 0,
-                incorrect_chars: 0,
-                rect: Rect::default(),
-                curr_text: Vec::with_capacity(target_text.len()),
-                target_text,
+    pub fn new(args: &[String]) -> Result<Self> {
+        let race = match args.get(1).map(String::as_str) {
+            Some("race") => Some(Race::new(args)?),
+            _ => None,
+        };
+
+        let live_stats_position = LiveStatsPosition::new(args)?;
+
+        let config = Config::load()?;
+        let keymap = Keymap::new(&config.keymap)?;
+
+        let input = Input::new(args);
+        let layout = Layout::new(args)?;
+        let show_keyboard = layout.is_some() || args.contains(&"-k".to_string());
+
+        let text_source = TextSource::new(args)?;
+
+        let home = match race.is_none() && args.contains(&"--home".to_string()) {
+            true => Some(Home::default()),
+            false => None,
+        };
+
+        let timer = Timer::new(args)?;
+
+        // clients get their text from the host
+        let target_text = match &race {
+            Some(race) if !race.is_host() => Vec::new(),
+            _ => App::gen_target_text(&text_source, timer.get_time_limit())?,
+        };
+
+        Ok(Self {
+            exit: false,
+            scroller: false,
+            timer,
+            countdown: None,
+            race,
+            error_mode: ErrorMode::new(args)?,
+            input,
+            keymap,
+            text_source,
+            palette: None,
+            home,
+            // replays don't count as personal bests
+            history: match args.contains(&"--headless".to_string()) {
+                true => History::default(),
+                false => History::load()?,
             },
-            Tui::enter()?
-        ))
+            history_view: None,
+            ngrams: match args.contains(&"--headless".to_string()) {
+                true => Ngrams::default(),
+                false => Ngrams::load()?,
+            },
+            show_ngrams: false,
+            practice: false,
+            recorded: false,
+            running: false,
+            session_results: Vec::new(),
+            previous_best: None,
+            flags: Vec::new(),
+            keystrokes: Vec::new(),
+            mistakes: Vec::new(),
+            hits: BTreeMap::new(),
+            layout,
+            show_keyboard,
+            keyboard_rect: None,
+            buttons: Vec::new(),
+            wrong_key: None,
+            fail_conditions: FailConditions::new(args)?,
+            failed: None,
+            live_stats: live_stats_position.is_some(),
+            live_stats_position: live_stats_position.unwrap_or(LiveStatsPosition::Top),
+            correct_chars: 0,
+            incorrect_chars: 0,
+            pending_letter: None,
+            last_counted: None,
+            rect: Rect::default(),
+            curr_text: Vec::with_capacity(target_text.len()),
+            extra_text: BTreeMap::new(),
+            target_text,
+        })
     }
 
     pub fn should_exit(&self) -> bool {
@@ -47,7 +337,7 @@ impl App {
             self.curr_text.len().saturating_sub(1)
         );
 
-        self.curr_text.truncate(word_start + ((word_start != 0) as usize));
+        self.truncate_curr_text(word_start + ((word_start != 0) as usize));
     }
 
     pub fn del_whitespaces(&mut self) {
@@ -55,81 +345,330 @@ impl App {
             .curr_text
             .iter()
             .enumerate()
-            .rfind(|(_, &c)| c != ' ');
+            .rfind(|(_, c)| *c != " ");
 
         if let Some((i, _)) = last_non_whitespace {
-            self.curr_text.truncate(i + 1)
+            self.truncate_curr_text(i + 1)
         } else {
-            self.curr_text.clear() 
+            self.truncate_curr_text(0)
         }
     }
 
-    pub fn jump_to_next_word(&mut self) {
-        if self.curr_text.iter().last() == Some(&' ') {
-            return;
+    /// also drops the extra letters of the words that got deleted
+    fn truncate_curr_text(&mut self, len: usize) {
+        self.curr_text.truncate(len);
+        self.extra_text.retain(|&idx, _| idx <= len);
+        // a deleted letter has to be typed right again
+        self.hits.retain(|&idx, _| idx < len);
+        self.pending_letter = self.pending_letter.filter(|&idx| idx < len);
+        self.last_counted = self.last_counted.filter(|&(idx, _)| idx < len);
+    }
+
+    pub fn pop_letter(&mut self) {
+        self.truncate_curr_text(self.curr_text.len().saturating_sub(1));
+    }
+
+    pub fn push_extra_char(&mut self, char: &str) -> Result<()> {
+        let extra = self.extra_text.entry(self.curr_text.len()).or_default();
+
+        if extra.len() >= MAX_EXTRA_CHARS {
+            return Ok(())
+        }
+
+        self.incorrect_chars += 1;
+        self.mistakes.push(self.curr_text.len());
+        self.wrong_key = char.chars().next().map(|c| (c, clock::now()));
+
+        match self.error_mode {
+            ErrorMode::StopOnLetter => (),
+            _ => extra.push(char.to_string()),
+        }
+
+        if extra.is_empty() {
+            self.extra_text.remove(&self.curr_text.len());
+        }
+
+        self.handle_mistake()
+    }
+
+    /// returns false if there was no extra letter at the cursor
+    pub fn pop_extra_char(&mut self) -> bool {
+        let idx = self.curr_text.len();
+
+        let Some(extra) = self.extra_text.get_mut(&idx) else {
+            return false;
+        };
+
+        extra.pop();
+
+        if extra.is_empty() {
+            self.extra_text.remove(&idx);
+        }
+
+        true
+    }
+
+    fn has_extra_chars(&self, idx: usize) -> bool {
+        self.extra_text.get(&idx).is_some_and(|extra| !extra.is_empty())
+    }
+
+    // used when the filler in front of the text changes, everything that points into the text moves with it
+    pub fn shift_text_indices(&mut self, by: isize) {
+        self.extra_text = std::mem::take(&mut self.extra_text)
+            .into_iter()
+            .map(|(idx, extra)| (idx.saturating_add_signed(by), extra))
+            .collect();
+
+        for (_, cursor) in &mut self.keystrokes {
+            *cursor = cursor.saturating_add_signed(by);
+        }
+
+        for idx in &mut self.mistakes {
+            *idx = idx.saturating_add_signed(by);
+        }
+
+        self.hits = std::mem::take(&mut self.hits)
+            .into_iter()
+            .map(|(idx, time)| (idx.saturating_add_signed(by), time))
+            .collect();
+
+        self.pending_letter = self.pending_letter.map(|idx| idx.saturating_add_signed(by));
+        self.last_counted = self.last_counted.map(|(idx, right)| (idx.saturating_add_signed(by), right));
+    }
+
+    /// target_text with the extra letters in it, and the cursor position in that text
+    pub fn get_display_text(&self) -> (Vec<String>, usize) {
+        let mut text = Vec::with_capacity(self.target_text.len());
+        let mut cursor = self.curr_text.len();
+
+        for (i, c) in self.target_text.iter().enumerate() {
+            if let Some(extra) = self.extra_text.get(&i) {
+                text.extend(extra.iter().cloned());
+            }
+
+            text.push(c.clone());
+        }
+
+        if let Some(extra) = self.extra_text.get(&self.target_text.len()) {
+            text.extend(extra.iter().cloned());
+        }
+
+        cursor += self.extra_text
+            .range(..=self.curr_text.len())
+            .map(|(_, extra)| extra.len())
+            .sum::<usize>();
+
+        (text, cursor)
+    }
+
+    pub fn jump_to_next_word(&mut self) -> Result<()> {
+        if self.curr_text.last().is_some_and(|c| c == " ") {
+            return Ok(())
+        }
+
+        if self.resolve_pending_letter()? {
+            return Ok(())
+        }
+
+        if self.error_mode == ErrorMode::StopOnWord && !self.is_curr_word_correct() {
+            return Ok(())
         }
 
         let next_whitespace_wrap = self.target_text
             .iter().enumerate().skip(self.curr_text.len())
-                .find(|(_, &c)| c == ' ');
+                .find(|(_, c)| *c == " ");
 
         if let Some((next_whitespace_idx, _)) = next_whitespace_wrap {
-            let fill = std::iter::repeat(' ').take(
-                (next_whitespace_idx + 1) - self.curr_text.len()
-            ).collect::<Vec<char>>();
+            let fill = vec![" ".to_string(); (next_whitespace_idx + 1) - self.curr_text.len()];
 
             self.curr_text.extend(fill);
         }
+
+        Ok(())
+    }
+
+    /// true if the word under the cursor is fully and correctly typed
+    fn is_curr_word_correct(&self) -> bool {
+        let curr_len = self.curr_text.len();
+
+        if self.target_text.get(curr_len).is_some_and(|c| c != " ") {
+            return false;
+        }
+
+        let word_start = get_prev_whitespace(&self.target_text, curr_len);
+
+        self.curr_text[word_start..] == self.target_text[word_start..curr_len]
+            && !self.has_extra_chars(curr_len)
+    }
+
+    /// finished, out of time or failed, nothing can be typed anymore
+    pub fn is_test_over(&self) -> bool {
+        self.is_finished_typing() || self.timer.is_out_of_time() || self.failed.is_some()
+    }
+
+    fn check_fail_conditions(&mut self) {
+        if self.failed.is_some() || !self.timer.is_started() || self.timer.is_stopped() || self.timer.is_out_of_time() {
+            return;
+        }
+
+        if self.timer.get_elapsed() < self.fail_conditions.grace {
+            return;
+        }
+
+        if let Some(min) = self.fail_conditions.min_accuracy {
+            if self.get_accuracy() < min {
+                self.failed = Some(FailReason::Accuracy(min));
+            }
+        }
+
+        if let Some(min) = self.fail_conditions.min_wpm {
+            if self.get_wpm() < min {
+                self.failed = Some(FailReason::Speed(min));
+            }
+        }
+
+        if self.failed.is_some() {
+            self.timer.stop();
+        }
     }
 
     pub fn is_finished_typing(&self) -> bool {
-        if self.curr_text.len() == self.target_text.len() {
+        if !self.curr_text.is_empty() && self.curr_text.len() == self.target_text.len() {
             // we can use this for both because the last curr word can't have whitespaces and the whitespace must be there
             let last_whitespace_idx = crate::util::get_prev_whitespace(&self.curr_text, self.curr_text.len() - 1);
 
-            let last_curr_word: String = self.curr_text[last_whitespace_idx..].iter().collect();
-            let last_target_word: String = self.target_text[last_whitespace_idx..].iter().collect();
+            let last_curr_word: String = self.curr_text[last_whitespace_idx..].concat();
+            let last_target_word: String = self.target_text[last_whitespace_idx..].concat();
 
-            return last_curr_word == last_target_word;
+            return last_curr_word == last_target_word && !self.has_extra_chars(self.curr_text.len());
         }
 
         false
     }
 
+    /// a combining mark typed on its own belongs to the letter before it
+    pub fn try_combine_char(&mut self, char: &str) -> Option<Result<()>> {
+        if self.has_extra_chars(self.curr_text.len()) {
+            return None;
+        }
+
+        let last = self.curr_text.last().filter(|c| *c != " ")?;
+        let combined: String = format!("{}{}", last, char).nfc().collect();
+
+        if combined.graphemes(true).count() != 1 {
+            return None;
+        }
+
+        let idx = self.curr_text.len() - 1;
+        self.uncount_letter(idx);
+        self.curr_text[idx] = combined;
+
+        Some(self.check_is_char_corr())
+    }
+
+    // takes back the count of a letter that is about to be checked again
+    fn uncount_letter(&mut self, idx: usize) {
+        match self.last_counted.take() {
+            Some((counted, true)) if counted == idx => {
+                self.correct_chars -= 1;
+                self.hits.remove(&idx);
+            },
+            Some((counted, false)) if counted == idx => {
+                self.incorrect_chars -= 1;
+
+                if let Some(pos) = self.mistakes.iter().rposition(|&mistake| mistake == idx) {
+                    self.mistakes.remove(pos);
+                }
+            },
+            _ => (),
+        }
+    }
+
     pub fn check_is_char_corr(&mut self) -> Result<()> {
-        let last_curr_char = self.curr_text[self.curr_text.len() - 1];
-        let last_target_char = self.target_text[self.curr_text.len() - 1];
+        let idx = self.curr_text.len() - 1;
+        let last_curr_char = &self.curr_text[idx];
+        let last_target_char = &self.target_text[idx];
+
+        self.pending_letter = None;
+
+        // the start of a letter with combining marks, wait for the rest of it
+        if last_curr_char != last_target_char
+            && last_target_char.nfd().collect::<String>().starts_with(last_curr_char.as_str())
+        {
+            self.pending_letter = Some(idx);
+            return Ok(());
+        }
 
         match last_curr_char == last_target_char {
-            true => self.correct_chars += 1,
-            false => {
-                self.incorrect_chars += 1;
+            true => {
+                self.correct_chars += 1;
+                self.hits.insert(idx, clock::now());
+                self.last_counted = Some((idx, true));
+                Ok(())
+            },
+            false => self.count_wrong_letter(idx),
+        }
+    }
 
-                if std::env::args().find(|i| i == "-d").is_some() {
-                    self.next_test()?;
-                };
-            }
+    /// the letter waiting for a combining mark is wrong once anything else is typed,
+    /// returns true if the mistake restarted the test
+    pub fn resolve_pending_letter(&mut self) -> Result<bool> {
+        match self.pending_letter.take() {
+            Some(idx) if idx + 1 == self.curr_text.len() => {
+                self.count_wrong_letter(idx)?;
+                Ok(matches!(self.error_mode, ErrorMode::SuddenDeath | ErrorMode::SuddenDeathRetry))
+            },
+            _ => Ok(false),
         }
+    }
 
-        Ok(())
+    fn count_wrong_letter(&mut self, idx: usize) -> Result<()> {
+        self.incorrect_chars += 1;
+        self.mistakes.push(idx);
+        self.last_counted = Some((idx, false));
+        self.wrong_key = self.curr_text[idx].chars().next().map(|c| (c, clock::now()));
+
+        if self.error_mode == ErrorMode::StopOnLetter {
+            self.curr_text.pop();
+        }
+
+        self.handle_mistake()
     }
 
-    fn gen_target_text(args: &Vec<String>) -> Result<Vec<char>> {
-        match args.contains(&"-q".to_string()) {
-            true => crate::util::get_random_quotes(),
-            false => crate::util::get_random_words(args)
+    fn handle_mistake(&mut self) -> Result<()> {
+        match self.error_mode {
+            ErrorMode::Normal | ErrorMode::StopOnWord | ErrorMode::StopOnLetter => Ok(()),
+            ErrorMode::SuddenDeathRetry => self.restart_test(),
+            // racers have to stay on the text the host picked
+            ErrorMode::SuddenDeath if self.race.is_some() => self.restart_test(),
+            ErrorMode::SuddenDeath => self.next_test(),
         }
     }
 
-    pub fn gen_scroller_filter(&self) -> Vec<char> {
-        std::iter::repeat(' ')
-            .take(self.rect.width as usize / 2)
-            .collect()
+    /// timed tests get enough words that nobody runs out before the time does
+    fn gen_target_text(source: &TextSource, time_limit: Option<Duration>) -> Result<Vec<String>> {
+        let word_count = match time_limit {
+            Some(limit) => source.word_count.max((limit.as_secs_f64() * MAX_WPM / 60.0).ceil() as usize),
+            None => source.word_count,
+        };
+
+        match source.quotes {
+            true => crate::util::get_random_quotes(source.quote_length),
+            false => crate::util::get_random_words(word_count, source.list.as_deref())
+        }
+    }
+
+    pub fn gen_scroller_filter(&self) -> Vec<String> {
+        vec![" ".to_string(); self.rect.width as usize / 2]
     }
 
     pub fn next_test(&mut self) -> Result<()> {
-        let args: Vec<String> = args().collect();
-        self.target_text = App::gen_target_text(&args)?;
+        self.set_target_text(App::gen_target_text(&self.text_source, self.timer.get_time_limit())?)
+    }
+
+    pub fn set_target_text(&mut self, target_text: Vec<String>) -> Result<()> {
+        self.target_text = target_text;
+        self.practice = false;
 
         self.restart_test()?;
 
@@ -140,11 +679,237 @@ impl App {
         Ok(())
     }
 
+    pub fn is_in_race_lobby(&self) -> bool {
+        self.race.as_ref().is_some_and(|race| race.is_in_lobby())
+    }
+
+    /// host only, picks a new text and starts the countdown for everyone
+    pub fn start_race(&mut self) -> Result<()> {
+        if !self.race.as_ref().is_some_and(|race| race.is_host()) {
+            return Ok(())
+        }
+
+        self.next_test()?;
+
+        let text = self.target_text[self.get_filler_len()..].to_vec();
+        let countdown = self.timer.get_countdown_time().unwrap_or(RACE_COUNTDOWN);
+
+        if let Some(race) = &mut self.race {
+            race.start(&text, countdown);
+        }
+
+        self.countdown = Some(Countdown::new(countdown));
+
+        Ok(())
+    }
+
+    /// with -c the test waits for ENTER before the countdown,
+    /// races are started by the host instead
+    pub fn is_waiting_for_start(&self) -> bool {
+        self.race.is_none()
+            && self.timer.get_countdown_time().is_some()
+            && self.countdown.is_none()
+            && !self.timer.is_started()
+    }
+
+    pub fn start_countdown(&mut self) {
+        if let Some(countdown_time) = self.timer.get_countdown_time() {
+            self.countdown = Some(Countdown::new(countdown_time));
+        }
+    }
+
+    /// called every tick and after every event,
+    /// returns if the screen changes with time alone and needs a redraw
+    pub fn tick(&mut self) -> Result<bool> {
+        if self.countdown.as_ref().is_some_and(|countdown| countdown.is_done()) {
+            self.countdown = None;
+            self.timer.start();
+        }
+
+        self.check_fail_conditions();
+        self.record_result()?;
+        self.update_race()?;
+
+        // one more redraw once the flash is over
+        let flashing = self.wrong_key.is_some();
+        if self.get_wrong_key().is_none() {
+            self.wrong_key = None;
+        }
+
+        // the test ending by itself (out of time, failed) changes the screen too
+        let running = self.timer.is_started() && !self.is_test_over();
+        let was_running = std::mem::replace(&mut self.running, running);
+
+        Ok(was_running || running || flashing || self.countdown.is_some() || self.race.is_some())
+    }
+
+    /// what results of the current test are compared against,
+    /// e.g: `30s`, `25 words`, `short quote, sudden death, colemak`
+    pub fn get_test_kind(&self) -> String {
+        let mut kind = vec![self.get_test_length()];
+
+        if self.error_mode != ErrorMode::Normal {
+            kind.push(self.error_mode.to_string());
+        }
+
+        if self.input.is_lenient() {
+            kind.push("lenient".to_string());
+        }
+
+        if let Some(layout) = &self.layout {
+            kind.push(layout.name.clone());
+        }
+
+        if self.practice {
+            kind.push("practice".to_string());
+        }
+
+        kind.join(", ")
+    }
+
+    /// the current test beat the best result of its kind
+    pub fn is_new_best(&self) -> bool {
+        self.get_flags().is_empty() && self.previous_best.as_ref().is_some_and(|best| self.get_wpm() > best.wpm)
+    }
+
+    pub fn flag(&mut self, flag: Flag) {
+        if !self.flags.contains(&flag) {
+            self.flags.push(flag);
+        }
+    }
+
+    pub fn log_keystroke(&mut self) {
+        let time = match self.timer.is_started() {
+            true => self.timer.get_elapsed(),
+            false => Duration::ZERO,
+        };
+
+        self.keystrokes.push((time, self.curr_text.len()));
+    }
+
+    /// the flags raised during the test and the ones the keystrokes and speed give away
+    pub fn get_flags(&self) -> Vec<Flag> {
+        let mut flags = self.flags.clone();
+
+        if is_too_uniform(&self.keystrokes) {
+            flags.push(Flag::Uniform);
+        }
+
+        if self.get_wpm() > MAX_WPM {
+            flags.push(Flag::TooFast);
+        }
+
+        flags
+    }
+
+    fn get_test_length(&self) -> String {
+        let text = match (&self.text_source.list, self.text_source.quote_length) {
+            (_, Some(length)) if self.text_source.quotes => format!("{} quote", length),
+            (_, None) if self.text_source.quotes => "quote".to_string(),
+            (Some(list), _) => format!("{} words ({})", self.text_source.word_count, list),
+            (None, _) => format!("{} words", self.text_source.word_count),
+        };
+
+        match self.timer.get_time_limit() {
+            Some(limit) if self.text_source.quotes => format!("{}s {}", limit.as_secs(), text),
+            // the word count only matters if the words run out before the time does
+            Some(limit) => match &self.text_source.list {
+                Some(list) => format!("{}s ({})", limit.as_secs(), list),
+                None => format!("{}s", limit.as_secs()),
+            },
+            None => text,
+        }
+    }
+
+    // failed tests are left out, races and practice are only kept for this session
+    fn record_result(&mut self) -> Result<()> {
+        if self.recorded || self.failed.is_some() {
+            return Ok(())
+        }
+
+        if !self.is_finished_typing() && !self.timer.is_out_of_time() {
+            return Ok(())
+        }
+
+        let result = self.get_result();
+
+        self.recorded = true;
+        self.session_results.push(result.clone());
+
+        if self.race.is_some() || self.practice {
+            return Ok(())
+        }
+
+        if result.is_valid() {
+            self.ngrams.add(&self.get_ngram_latencies())?;
+        }
+
+        self.previous_best = self.history.get_best(&result.test).cloned();
+        self.history.add(result)
+    }
+
+    /// the stats of the current test so far
+    pub fn get_result(&self) -> TestResult {
+        TestResult {
+            test: self.get_test_kind(),
+            wpm: self.get_wpm(),
+            accuracy: self.get_accuracy(),
+            time: self.timer.get_time().as_secs_f64(),
+            words: self.get_word_progress().0,
+            correct: self.get_correct(),
+            incorrect: self.get_incorrect(),
+            corrected: self.get_corrected(),
+            uncorrected: self.get_uncorrected(),
+            missed: self.get_missed(),
+            finished_at: get_unix_time(),
+            flags: self.get_flags(),
+            word_speeds: self.get_word_speeds(),
+            word_errors: self.get_word_errors(),
+        }
+    }
+
+    fn update_race(&mut self) -> Result<()> {
+        let Some(race) = &mut self.race else {
+            return Ok(())
+        };
+
+        for event in race.poll() {
+            match event {
+                RaceEvent::Text(text) => self.set_target_text(text)?,
+                RaceEvent::Start(countdown) => {
+                    self.restart_test()?;
+                    self.countdown = Some(Countdown::new(countdown));
+                },
+            }
+        }
+
+        let progress = self.get_progress();
+        let wpm = self.get_wpm();
+
+        if let Some(race) = &mut self.race {
+            race.send_progress(progress, wpm);
+        }
+
+        Ok(())
+    }
+
     pub fn restart_test(&mut self) -> Result<()> {
         self.correct_chars = 0;
         self.incorrect_chars = 0;
+        self.pending_letter = None;
+        self.last_counted = None;
         self.curr_text.clear();
+        self.extra_text.clear();
+        self.input.reset();
         self.timer.reset();
+        self.countdown = None;
+        self.failed = None;
+        self.recorded = false;
+        self.previous_best = None;
+        self.flags.clear();
+        self.keystrokes.clear();
+        self.mistakes.clear();
+        self.hits.clear();
 
         if self.scroller {
             self.curr_text = self.gen_scroller_filter();
@@ -153,36 +918,229 @@ impl App {
         Ok(())
     }
 
+    pub fn get_progress(&self) -> f64 {
+        let filler_len = self.get_filler_len();
+        let text_len = self.target_text.len().saturating_sub(filler_len);
+
+        if text_len == 0 {
+            return 0.0
+        }
+
+        if self.is_finished_typing() {
+            return 1.0
+        }
+
+        self.curr_text.len().saturating_sub(filler_len) as f64 / text_len as f64
+    }
+
+    /// (start, end) of every word in target_text, end being the index of the whitespace after it
+    pub fn get_word_ranges(&self) -> Vec<(usize, usize)> {
+        let mut ranges = Vec::new();
+        let mut start = 0;
+
+        for (i, c) in self.target_text.iter().enumerate() {
+            if c == " " {
+                if i > start {
+                    ranges.push((start, i));
+                }
+
+                start = i + 1;
+            }
+        }
+
+        if self.target_text.len() > start {
+            ranges.push((start, self.target_text.len()));
+        }
+
+        ranges
+    }
+
+    pub fn is_word_correct(&self, (start, end): (usize, usize)) -> bool {
+        self.curr_text.get(start..end) == Some(&self.target_text[start..end])
+            && !self.has_extra_chars(end)
+    }
+
+    /// how fast each word typed to its end was, in wpm
+    pub fn get_word_speeds(&self) -> Vec<(String, f64)> {
+        let mut speeds = Vec::new();
+
+        for (start, end) in self.get_word_ranges() {
+            // a word ends when the cursor first gets to its end
+            let Some(end_idx) = self.keystrokes.iter().position(|&(_, cursor)| cursor >= end) else { break };
+
+            // and starts with the whitespace before it (or the first key of the test)
+            let Some(&(started, _)) = self.keystrokes[..end_idx].iter()
+                .rev()
+                .find(|&&(_, cursor)| cursor <= start)
+                .or(self.keystrokes.first()) else { break };
+
+            let secs = self.keystrokes[end_idx].0.saturating_sub(started).as_secs_f64();
+
+            if secs > 0.0 {
+                speeds.push((self.target_text[start..end].concat(), 60.0 / secs));
+            }
+        }
+
+        speeds
+    }
+
+    /// the time per key of every bigram and trigram typed right, within words
+    pub fn get_ngram_latencies(&self) -> Vec<(String, Duration)> {
+        let mut latencies = Vec::new();
+
+        for (&idx, &time) in &self.hits {
+            for len in [2, 3] {
+                let Some(first) = (idx + 1).checked_sub(len) else { continue };
+
+                // whitespaces are never hits, so ngrams don't go over words
+                if (first..idx).any(|i| !self.hits.contains_key(&i)) {
+                    continue;
+                }
+
+                let latency = time.saturating_duration_since(self.hits[&first]) / (len as u32 - 1);
+
+                if !latency.is_zero() && latency <= MAX_NGRAM_LATENCY {
+                    latencies.push((self.target_text[first..=idx].concat(), latency));
+                }
+            }
+        }
+
+        latencies
+    }
+
+    /// how many wrong letters each word got, the most first
+    pub fn get_word_errors(&self) -> Vec<(String, u32)> {
+        let mut errors: Vec<(String, u32)> = Vec::new();
+
+        for (start, end) in self.get_word_ranges() {
+            let count = self.mistakes.iter().filter(|&idx| (start..=end).contains(idx)).count() as u32;
+            if count == 0 {
+                continue;
+            }
+
+            let word = self.target_text[start..end].concat();
+
+            match errors.iter_mut().find(|(w, _)| *w == word) {
+                Some((_, total)) => *total += count,
+                None => errors.push((word, count)),
+            }
+        }
+
+        errors.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+        errors
+    }
+
+    /// words that still have errors, without the one the time ran out on if it was right so far
+    pub fn get_missed_words(&self) -> Vec<&[String]> {
+        let typed = self.curr_text.len();
+
+        self.get_word_ranges()
+            .into_iter()
+            .filter(|&(start, _)| start < typed)
+            .filter(|&word| !self.is_word_correct(word))
+            .filter(|&(start, end)| end <= typed || self.curr_text[start..] != self.target_text[start..typed])
+            .map(|(start, end)| &self.target_text[start..end])
+            .collect()
+    }
+
+    /// a practice test of the missed words, each typed a few times
+    pub fn practice_missed_words(&mut self) -> Result<()> {
+        let mut words: Vec<Vec<String>> = Vec::new();
+
+        for word in self.get_missed_words() {
+            if !words.iter().any(|w| w == word) {
+                words.extend(std::iter::repeat_n(word.to_vec(), MISSED_WORD_REPEATS));
+            }
+        }
+
+        if words.is_empty() {
+            return Ok(())
+        }
+
+        self.set_target_text(words.join(&" ".to_string()))?;
+        self.practice = true;
+
+        Ok(())
+    }
+
     pub fn get_wpm(&self) -> f64 {
-        let target_words: Vec<String> = self.target_text
-            .split(|c| c.is_whitespace())
-            .map(|word| word.iter().collect())
-            .collect();
+        let minutes = self.timer.get_time().as_secs_f64() / 60.0;
 
-        let curr_words: Vec<String> = self.curr_text
-            .split(|c| c.is_whitespace())
-            .map(|word| word.iter().collect())
-            .collect();
+        if minutes == 0.0 {
+            return 0.0
+        }
 
-        target_words.iter()
-            .zip(curr_words)
-            .filter(|(t, c)| *t == c)
+        self.get_word_ranges()
+            .into_iter()
+            .filter(|&word| self.is_word_correct(word))
             .count() as f64
-            / (self.timer.get_time().as_secs_f64() / 60.0)
+            / minutes
     }
 
     pub fn get_accuracy(&self) -> f64 {
+        if self.correct_chars + self.incorrect_chars == 0 {
+            return 100.0
+        }
+
         (self.correct_chars as f64 / (self.correct_chars + self.incorrect_chars) as f64) * 100.0
     }
 
+    /// (words typed, words in the test)
+    pub fn get_word_progress(&self) -> (usize, usize) {
+        let filler_len = self.get_filler_len();
+        let text = &self.target_text[filler_len.min(self.target_text.len())..];
+        let typed = self.curr_text.len().saturating_sub(filler_len).min(text.len());
+
+        (
+            text[..typed].iter().filter(|c| *c == " ").count() + self.is_finished_typing() as usize,
+            text.iter().filter(|c| *c == " ").count() + 1
+        )
+    }
+
+    pub fn get_live_stats_position(&self) -> Option<LiveStatsPosition> {
+        self.live_stats.then_some(self.live_stats_position)
+    }
+
+    pub fn toggle_live_stats(&mut self) {
+        self.live_stats = !self.live_stats;
+    }
+
     pub fn get_correct(&self) -> u32 {
         self.correct_chars
     }
 
+    /// every wrong keystroke, even the ones fixed later
     pub fn get_incorrect(&self) -> u32 {
         self.incorrect_chars
     }
 
+    /// wrong letters (and extra letters) that are still in the text,
+    /// a letter still waiting for its combining mark isn't counted either way yet
+    pub fn get_uncorrected(&self) -> u32 {
+        let wrong_chars = self.curr_text.iter()
+            .zip(&self.target_text)
+            .enumerate()
+            .filter(|&(idx, (c, t))| c != t && c != " " && self.pending_letter != Some(idx))
+            .count();
+
+        let extra_chars: usize = self.extra_text.values().map(Vec::len).sum();
+
+        (wrong_chars + extra_chars) as u32
+    }
+
+    /// wrong keystrokes that were fixed with backspace
+    pub fn get_corrected(&self) -> u32 {
+        self.incorrect_chars.saturating_sub(self.get_uncorrected())
+    }
+
+    /// letters skipped by jumping to the next word
+    pub fn get_missed(&self) -> u32 {
+        self.curr_text.iter()
+            .zip(&self.target_text)
+            .filter(|(c, t)| *c == " " && *t != " ")
+            .count() as u32
+    }
+
     pub fn is_in_scroller_mode(&self) -> bool {
         self.scroller
     }
@@ -192,27 +1150,15 @@ impl App {
     }
 
     pub fn update_rect(&mut self, frame_rect: Rect) {
-        let xperc: f32;
-        let yperc: f32;
-
-        if frame_rect.height < 15 {
-            yperc = 0.20
-        } else {
-            yperc = 0.35f32
-        }
-
-        if frame_rect.width < 80 {
-            xperc = 0.05
-        } else {
-            xperc = 0.25f32;
-        }
+        let yperc = if frame_rect.height < 15 { 0.20 } else { 0.35f32 };
+        let xperc = if frame_rect.width < 80 { 0.05 } else { 0.25f32 };
 
         let x = (frame_rect.width as f32 * xperc) as u16;
         let y: u16;
         let width = frame_rect.width - x * 2;
         let height: u16;
 
-        if self.scroller && !self.is_finished_typing() && !self.timer.is_out_of_time() {
+        if self.scroller && !self.is_test_over() {
             y = frame_rect.height / 2;
             height = 1;
         } else {
@@ -221,25 +1167,139 @@ impl App {
         }
 
         self.rect = Rect { x, y, width, height };
+        self.keyboard_rect = None;
+        self.buttons = match self.is_test_over() && self.race.is_none() {
+            true if self.get_missed_words().is_empty() => get_button_rects(&[Button::Next, Button::Retry, Button::History], frame_rect),
+            true => get_button_rects(&[Button::Next, Button::Retry, Button::PracticeMissed, Button::History], frame_rect),
+            false => Vec::new(),
+        };
+
+        if !self.show_keyboard || self.is_test_over() || frame_rect.width < Keyboard::WIDTH {
+            return;
+        }
+
+        // the text gets shorter to make room for the keyboard below it
+        let max_height = frame_rect.height.saturating_sub(Keyboard::HEIGHT + 2 + y);
+        if max_height < height.min(3) {
+            return;
+        }
+
+        self.rect.height = height.min(max_height);
+        self.keyboard_rect = Some(Rect {
+            x: (frame_rect.width - Keyboard::WIDTH) / 2,
+            y: self.rect.bottom() + 2,
+            width: Keyboard::WIDTH,
+            height: Keyboard::HEIGHT,
+        });
     }
 
     pub fn get_rect(&self) -> Rect { self.rect }
 
+    pub fn get_keyboard_rect(&self) -> Option<Rect> { self.keyboard_rect }
+
+    pub fn get_buttons(&self) -> &[(Button, Rect)] { &self.buttons }
+
+    pub fn get_button_at(&self, x: u16, y: u16) -> Option<Button> {
+        self.buttons.iter()
+            .find(|(_, rect)| rect.contains(Position { x, y }))
+            .map(|(button, _)| *button)
+    }
+
+    /// the test restarts from the word drawn at `x`, `y` as practice
+    pub fn restart_from(&mut self, x: u16, y: u16) -> Result<()> {
+        if self.scroller || self.race.is_some() || self.countdown.is_some() || self.is_test_over() || self.timer.is_paused() {
+            return Ok(())
+        }
+
+        let Some(idx) = self.get_text_idx_at(x, y) else { return Ok(()) };
+
+        if self.target_text[idx] == " " {
+            return Ok(())
+        }
+
+        let word_start = match get_prev_whitespace(&self.target_text, idx) {
+            0 if self.target_text[0] != " " => 0,
+            whitespace => whitespace + 1,
+        };
+
+        self.target_text.drain(..word_start);
+        self.practice = true;
+        self.restart_test()
+    }
+
+    // the index in target_text of what is drawn at `x`, `y` in the wrapped text
+    fn get_text_idx_at(&self, x: u16, y: u16) -> Option<usize> {
+        if !self.rect.contains(Position { x, y }) {
+            return None;
+        }
+
+        let (text, _) = self.get_display_text();
+        let line_starts = get_line_starts(&text, self.rect.width as usize);
+
+        let line = (y - self.rect.y) as usize;
+        let start = *line_starts.get(line)?;
+        let end = line_starts.get(line + 1).copied().unwrap_or(text.len());
+
+        let col = (x - self.rect.x) as usize;
+        let mut width = 0;
+        let display_idx = (start..end).find(|&i| {
+            width += text[i].width();
+            width > col
+        })?;
+
+        // extra letters are drawn before the whitespace they were typed at, as part of the word before it
+        let mut drawn = 0;
+        for i in 0..self.target_text.len() {
+            drawn += self.extra_text.get(&i).map_or(0, Vec::len);
+
+            if display_idx < drawn {
+                return Some(i.saturating_sub(1));
+            }
+
+            if display_idx == drawn {
+                return Some(i);
+            }
+
+            drawn += 1;
+        }
+
+        self.target_text.len().checked_sub(1)
+    }
+
+    pub fn toggle_keyboard(&mut self) {
+        self.show_keyboard = !self.show_keyboard;
+    }
+
+    /// the last wrongly pressed key, while it's still flashing
+    pub fn get_wrong_key(&self) -> Option<char> {
+        self.wrong_key
+            .filter(|(_, time)| clock::elapsed(*time) < WRONG_KEY_FLASH)
+            .map(|(c, _)| c)
+    }
+
     pub fn update_cursor(&mut self, frame: &mut ratatui::Frame) {
         if self.scroller {
             frame.set_cursor(self.rect.x + self.rect.width / 2, self.rect.y);
             return;
         }
 
-        let (x, y) = get_xy_wrapped(&self.curr_text, &self.target_text, self.rect);
+        let (text, cursor) = self.get_display_text();
+        let (x, y) = get_xy_wrapped(cursor, &text, self.rect);
         frame.set_cursor(x, y)
     }
 
+    fn get_filler_len(&self) -> usize {
+        match self.scroller {
+            true => self.target_text.iter().take_while(|c| *c == " ").count(),
+            false => 0,
+        }
+    }
+
     // used in scroller mode to center text with 0 x scroll
     pub fn adjust_filler_txt(&mut self) {
         let filler_len = self.target_text
             .iter()
-            .take_while(|&c| *c == ' ')
+            .take_while(|c| *c == " ")
             .count();
 
         let needed_filler_len = self.get_rect().width as usize / 2;
@@ -247,52 +1307,171 @@ impl App {
         if filler_len > needed_filler_len { // screen width deincreased
             self.curr_text.drain(0..filler_len - needed_filler_len);
             self.target_text.drain(0..filler_len - needed_filler_len);
+            self.shift_text_indices(-((filler_len - needed_filler_len) as isize));
 
         } else if filler_len < needed_filler_len { // screen width increased
-            let filler = std::iter::repeat(' ')
-                .take(needed_filler_len - filler_len)
-                .collect::<Vec<char>>();
+            let filler = vec![" ".to_string(); needed_filler_len - filler_len];
 
             self.curr_text.splice(0..0, filler.clone());
             self.target_text.splice(0..0, filler);
+            self.shift_text_indices((needed_filler_len - filler_len) as isize);
         }
     }
 }
 
-pub fn get_xy_wrapped(curr_text: &Vec<char>, target_text: &Vec<char>, rect: Rect) -> (u16, u16) {
-    let mut num_rows = rect.y;
+// a program types with the same (or no) time between keys
+fn is_too_uniform(keystrokes: &[(Duration, usize)]) -> bool {
+    if keystrokes.len() < MIN_KEYSTROKES {
+        return false;
+    }
+
+    let intervals: Vec<f64> = keystrokes
+        .windows(2)
+        .map(|keys| keys[1].0.saturating_sub(keys[0].0).as_secs_f64())
+        .collect();
 
-    if curr_text.len() != 0 {
-        let mut curr_line_width = rect.width as usize;
-        let mut last_line_width = 0;
+    let mean = intervals.iter().sum::<f64>() / intervals.len() as f64;
+    let variance = intervals.iter().map(|interval| (interval - mean).powi(2)).sum::<f64>() / intervals.len() as f64;
 
-        loop {
-            if curr_line_width < target_text.len() {
-                // check if the char at index rect.width is an whitespace 
-                if target_text[curr_line_width - 1] != ' ' {
+    variance.sqrt() <= mean * MIN_KEYSTROKE_VARIATION
+}
 
-                    let whitespace_before_word = crate::util::get_prev_whitespace(target_text, curr_line_width) + 1;
-                    let word_end_idx = crate::util::get_next_whitespace(target_text, curr_line_width);
+/// index of the first grapheme of every line when `text` is word wrapped to `width`
+pub fn get_line_starts(text: &[String], width: usize) -> Vec<usize> {
+    let mut line_starts = vec![0];
+    let mut line_width = 0;
+    let mut i = 0;
 
-                    let word_length = word_end_idx - whitespace_before_word;
+    while i < text.len() {
+        // whitespaces stay at the end of the line they were typed on
+        if text[i] == " " {
+            line_width += 1;
+            i += 1;
+            continue;
+        }
 
-                    if curr_line_width - whitespace_before_word < word_length {
-                        curr_line_width = whitespace_before_word
-                    } else { curr_line_width += 1 } // if the curr_line_width indexes the end char of an word we +1
-                }                                   // so curr_line_width indexes the whitespace
+        let word_end = text[i..].iter().position(|c| c == " ").map_or(text.len(), |len| i + len);
+        let word_width: usize = text[i..word_end].iter().map(|c| c.width()).sum();
 
-                // if at the next line
-                if curr_text.len() >= curr_line_width {
-                    num_rows += 1;
+        if line_width > 0 && line_width + word_width > width {
+            line_starts.push(i);
+            line_width = 0;
+        }
 
-                    last_line_width = curr_line_width;
-                    curr_line_width += rect.width as usize;
-                } else { break }
-            } else { break }
+        // words longer than a line get broken up
+        for (j, c) in text.iter().enumerate().take(word_end).skip(i) {
+            if line_width > 0 && line_width + c.width() > width {
+                line_starts.push(j);
+                line_width = 0;
+            }
+
+            line_width += c.width();
         }
 
-        return (((curr_text.len() - last_line_width) as u16) + rect.x, num_rows)
+        i = word_end;
     }
 
-    return (rect.x, rect.y);
+    line_starts
+}
+
+// in a row at the bottom of the screen
+fn get_button_rects(buttons: &[Button], frame_rect: Rect) -> Vec<(Button, Rect)> {
+    const GAP: u16 = 2;
+
+    let widths: Vec<u16> = buttons.iter().map(|button| button.name().len() as u16 + 2).collect();
+    let total_width = widths.iter().sum::<u16>() + GAP * (widths.len() as u16 - 1);
+
+    if frame_rect.width < total_width || frame_rect.height < 3 {
+        return Vec::new();
+    }
+
+    let mut x = (frame_rect.width - total_width) / 2;
+    let y = frame_rect.height - 2;
+
+    buttons.iter().zip(widths)
+        .map(|(&button, width)| {
+            let rect = Rect { x, y, width, height: 1 };
+            x += width + GAP;
+            (button, rect)
+        })
+        .collect()
+}
+
+/// position of the cursor at index `cursor` of the wrapped `text`
+pub fn get_xy_wrapped(cursor: usize, text: &[String], rect: Rect) -> (u16, u16) {
+    let line_starts = get_line_starts(text, rect.width as usize);
+
+    let line = line_starts.iter().rposition(|&start| start <= cursor).unwrap_or(0);
+    let x: usize = text[line_starts[line]..cursor.min(text.len())].iter().map(|c| c.width()).sum();
+
+    (rect.x + x as u16, rect.y + line as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::to_graphemes;
+
+    fn keystrokes(intervals_ms: &[u64]) -> Vec<(Duration, usize)> {
+        let mut time = Duration::ZERO;
+
+        std::iter::once((time, 0))
+            .chain(intervals_ms.iter().enumerate().map(|(i, &ms)| {
+                time += Duration::from_millis(ms);
+                (time, i + 1)
+            }))
+            .collect()
+    }
+
+    #[test]
+    fn too_few_keystrokes_are_never_uniform() {
+        assert!(!is_too_uniform(&[]));
+        assert!(!is_too_uniform(&keystrokes(&[])));
+        assert!(!is_too_uniform(&keystrokes(&[100])));
+        assert!(!is_too_uniform(&keystrokes(&[100; MIN_KEYSTROKES - 2])));
+    }
+
+    #[test]
+    fn keys_at_the_same_pace_are_uniform() {
+        assert!(is_too_uniform(&keystrokes(&[100; MIN_KEYSTROKES])));
+        // all at once, e.g: a replayed paste
+        assert!(is_too_uniform(&keystrokes(&[0; MIN_KEYSTROKES])));
+    }
+
+    #[test]
+    fn human_typing_is_not_uniform() {
+        let intervals: Vec<u64> = (0..MIN_KEYSTROKES as u64).map(|i| 80 + i % 4 * 40).collect();
+
+        assert!(!is_too_uniform(&keystrokes(&intervals)));
+    }
+
+    #[test]
+    fn wide_graphemes_take_two_columns() {
+        let text = to_graphemes("日本 語");
+
+        assert_eq!(get_line_starts(&text, 4), vec![0, 3]);
+        assert_eq!(get_line_starts(&text, 7), vec![0]);
+        assert_eq!(get_xy_wrapped(2, &text, Rect::new(10, 5, 4, 3)), (14, 5));
+        assert_eq!(get_xy_wrapped(3, &text, Rect::new(10, 5, 4, 3)), (10, 6));
+    }
+
+    #[test]
+    fn wide_words_longer_than_a_line_are_broken_up() {
+        let text = to_graphemes("日本語");
+
+        assert_eq!(get_line_starts(&text, 4), vec![0, 2]);
+        assert_eq!(get_xy_wrapped(3, &text, Rect::new(0, 0, 4, 3)), (2, 1));
+    }
+
+    #[test]
+    fn combining_marks_take_no_column() {
+        // q has no precomposed accented form, so the mark stays in the grapheme
+        let text = to_graphemes("q\u{301}q\u{301} ab");
+
+        assert_eq!(text.len(), 5);
+        assert_eq!(get_line_starts(&text, 4), vec![0, 3]);
+        assert_eq!(get_line_starts(&text, 5), vec![0]);
+        assert_eq!(get_xy_wrapped(2, &text, Rect::new(0, 0, 4, 3)), (2, 0));
+        assert_eq!(get_xy_wrapped(5, &text, Rect::new(0, 0, 4, 3)), (2, 1));
+    }
 }
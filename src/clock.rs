@@ -0,0 +1,19 @@
+use std::{cell::Cell, time::{Duration, Instant}};
+
+thread_local! {
+    /// set by replays so the stats don't depend on how fast the replay runs,
+    /// per thread so tests can each have their own time
+    static VIRTUAL_NOW: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+pub fn now() -> Instant {
+    VIRTUAL_NOW.get().unwrap_or_else(Instant::now)
+}
+
+pub fn set_now(now: Instant) {
+    VIRTUAL_NOW.set(Some(now));
+}
+
+pub fn elapsed(since: Instant) -> Duration {
+    now().saturating_duration_since(since)
+}
@@ -0,0 +1,18 @@
+use std::collections::BTreeMap;
+use anyhow::Result;
+use serde::Deserialize;
+use crate::util::load_ron;
+
+/// `~/.config/tt-rs/config.ron`, everything in it is optional
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Config {
+    /// action name to the key chords that trigger it, e.g: `"restart": ["alt+r", "f5"]`
+    pub keymap: BTreeMap<String, Vec<String>>,
+}
+
+impl Config {
+    pub fn load() -> Result<Self> {
+        load_ron("config.ron", "config file")
+    }
+}
@@ -0,0 +1,102 @@
+use anyhow::Result;
+use crate::history::{join_flags, TestResult};
+
+#[derive(Default)]
+enum Format {
+    #[default]
+    Json,
+    Csv,
+}
+
+/// prints results to stdout after the tui is closed, the tui itself draws to stderr
+#[derive(Default)]
+pub struct Export {
+    format: Format,
+    /// every test of the session instead of only the last one
+    all: bool,
+}
+
+impl Export {
+    pub fn new(args: &[String]) -> Result<Option<Self>> {
+        let format = match (args.contains(&"--json".to_string()), args.contains(&"--csv".to_string())) {
+            (true, true) => anyhow::bail!("use only one of --json and --csv"),
+            (true, false) => Format::Json,
+            (false, true) => Format::Csv,
+            (false, false) => return Ok(None),
+        };
+
+        Ok(Some(Self {
+            format,
+            all: args.contains(&"--all".to_string()),
+        }))
+    }
+
+    pub fn print(&self, results: &[TestResult]) -> Result<()> {
+        let results = match self.all {
+            true => results,
+            false => &results[results.len().saturating_sub(1)..],
+        };
+
+        match self.format {
+            Format::Json if self.all => println!("{}", serde_json::to_string(results)?),
+            Format::Json => match results.first() {
+                Some(result) => println!("{}", serde_json::to_string(result)?),
+                None => println!("null"),
+            },
+            Format::Csv => {
+                println!("test,wpm,accuracy,time,words,correct,incorrect,corrected,uncorrected,missed,finished_at,flags");
+
+                for result in results {
+                    println!(
+                        "{},{:.2},{:.2},{:.3},{},{},{},{},{},{},{},{}",
+                        escape_csv(&result.test),
+                        result.wpm,
+                        result.accuracy,
+                        result.time,
+                        result.words,
+                        result.correct,
+                        result.incorrect,
+                        result.corrected,
+                        result.uncorrected,
+                        result.missed,
+                        result.finished_at,
+                        escape_csv(&join_flags(&result.flags)),
+                    );
+                }
+            },
+        }
+
+        Ok(())
+    }
+}
+
+// test kinds can have commas in them (e.g: `25 words, sudden death`)
+fn escape_csv(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_fields_stay_as_they_are() {
+        assert_eq!(escape_csv("25 words"), "25 words");
+        assert_eq!(escape_csv(""), "");
+    }
+
+    #[test]
+    fn fields_with_separators_are_quoted() {
+        assert_eq!(escape_csv("25 words, sudden death"), "\"25 words, sudden death\"");
+        assert_eq!(escape_csv("a\nb"), "\"a\nb\"");
+        assert_eq!(escape_csv("a\r\nb"), "\"a\r\nb\"");
+    }
+
+    #[test]
+    fn quotes_are_doubled() {
+        assert_eq!(escape_csv("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
@@ -0,0 +1,197 @@
+use std::{collections::BTreeMap, fmt, time::{SystemTime, UNIX_EPOCH}};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use crate::util::{load_ron, save_ron};
+
+/// why a result might not have been typed fairly
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum Flag {
+    Pasted,
+    LostFocus,
+    /// the time between keys barely changed
+    Uniform,
+    TooFast,
+}
+
+impl fmt::Display for Flag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Flag::Pasted => write!(f, "pasted input"),
+            Flag::LostFocus => write!(f, "lost focus"),
+            Flag::Uniform => write!(f, "uniform keystrokes"),
+            Flag::TooFast => write!(f, "too fast"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct TestResult {
+    /// what kind of test it was, results are only compared within a kind (e.g: `30s`, `25 words`)
+    pub test: String,
+    pub wpm: f64,
+    pub accuracy: f64,
+    /// in secs
+    pub time: f64,
+    /// words typed
+    pub words: usize,
+    pub correct: u32,
+    pub incorrect: u32,
+    pub corrected: u32,
+    pub uncorrected: u32,
+    pub missed: u32,
+    /// unix time in secs of when the test was finished
+    pub finished_at: u64,
+    /// a result with flags is not valid and never a personal best
+    pub flags: Vec<Flag>,
+    /// the wpm of each word typed to its end
+    pub word_speeds: Vec<(String, f64)>,
+    /// the words typed with wrong letters and how many
+    pub word_errors: Vec<(String, u32)>,
+}
+
+impl TestResult {
+    /// `today`, `yesterday` or `5 days ago`
+    pub fn get_age(&self) -> String {
+        match get_unix_time().saturating_sub(self.finished_at) / (24 * 60 * 60) {
+            0 => "today".to_string(),
+            1 => "yesterday".to_string(),
+            days => format!("{} days ago", days),
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.flags.is_empty()
+    }
+}
+
+/// e.g: `pasted input, lost focus`
+pub fn join_flags(flags: &[Flag]) -> String {
+    flags.iter()
+        .map(|flag| flag.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub fn get_unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+/// how many words go into trouble.ron
+const TROUBLE_WORDS: usize = 50;
+
+/// every finished test, kept in ~/.config/tt-rs/history.ron
+#[derive(Default)]
+pub struct History {
+    results: Vec<TestResult>,
+    /// only loaded histories are saved
+    persist: bool,
+}
+
+impl History {
+    pub fn load() -> Result<Self> {
+        Ok(Self { results: load_ron("history.ron", "history file")?, persist: true })
+    }
+
+    // the trouble words are saved next to the history, --list trouble reads them from there
+    fn save(&self) -> Result<()> {
+        save_ron("history.ron", "history file", &self.results)?;
+
+        let trouble_words = self.get_trouble_words();
+        if trouble_words.is_empty() {
+            return Ok(())
+        }
+
+        save_ron("trouble.ron", "word list", &trouble_words)
+    }
+
+    /// the oldest first
+    pub fn get_results(&self) -> &[TestResult] {
+        &self.results
+    }
+
+    pub fn add(&mut self, result: TestResult) -> Result<()> {
+        self.results.push(result);
+
+        match self.persist {
+            true => self.save(),
+            false => Ok(()),
+        }
+    }
+
+    /// the words missed most often in every test, then the slowest on average
+    pub fn get_trouble_words(&self) -> Vec<String> {
+        // errors, sum of wpm and how many times it was typed for each word
+        let mut words: BTreeMap<&str, (u32, f64, u32)> = BTreeMap::new();
+
+        for result in &self.results {
+            for (word, wpm) in &result.word_speeds {
+                let (_, wpm_sum, typed) = words.entry(word).or_default();
+                *wpm_sum += wpm;
+                *typed += 1;
+            }
+
+            for (word, errors) in &result.word_errors {
+                words.entry(word).or_default().0 += errors;
+            }
+        }
+
+        let mut words: Vec<(&str, u32, f64)> = words.into_iter()
+            .map(|(word, (errors, wpm_sum, typed))| (word, errors, wpm_sum / typed.max(1) as f64))
+            .collect();
+
+        words.sort_by(|a, b| b.1.cmp(&a.1).then(a.2.total_cmp(&b.2)));
+
+        words.into_iter()
+            .take(TROUBLE_WORDS)
+            .map(|(word, _, _)| word.to_string())
+            .collect()
+    }
+
+    /// the fastest result of a kind of test
+    pub fn get_best(&self, test: &str) -> Option<&TestResult> {
+        self.results.iter()
+            .filter(|result| result.test == test && result.is_valid())
+            .max_by(|a, b| a.wpm.total_cmp(&b.wpm))
+    }
+
+    /// the fastest result of each kind of test, the most recently set first
+    pub fn get_bests(&self) -> Vec<&TestResult> {
+        let mut bests: Vec<&TestResult> = Vec::new();
+
+        for result in self.results.iter().filter(|result| result.is_valid()) {
+            match bests.iter_mut().find(|best| best.test == result.test) {
+                Some(best) if result.wpm > best.wpm => *best = result,
+                Some(_) => (),
+                None => bests.push(result),
+            }
+        }
+
+        bests.sort_by_key(|best| std::cmp::Reverse(best.finished_at));
+        bests
+    }
+}
+
+/// every past result, the latest first, opened from the results screen
+#[derive(Default)]
+pub struct HistoryView {
+    /// how many results are scrolled past
+    scroll: usize,
+}
+
+impl HistoryView {
+    pub fn get_scroll(&self) -> usize {
+        self.scroll
+    }
+
+    pub fn scroll_up(&mut self, lines: usize) {
+        self.scroll = self.scroll.saturating_sub(lines);
+    }
+
+    /// the last result always stays on screen
+    pub fn scroll_down(&mut self, lines: usize, len: usize) {
+        self.scroll = (self.scroll + lines).min(len.saturating_sub(1));
+    }
+}
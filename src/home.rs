@@ -0,0 +1,67 @@
+use std::fmt;
+use crate::util::QuoteLength;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Preset {
+    /// secs
+    Time(u64),
+    Words(usize),
+    Quote(QuoteLength),
+    /// opens the palette with the current settings
+    Custom,
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Preset::Time(secs) => write!(f, "{}s", secs),
+            Preset::Words(count) => write!(f, "{}", count),
+            Preset::Quote(length) => write!(f, "{}", length),
+            Preset::Custom => write!(f, "custom"),
+        }
+    }
+}
+
+/// presets grouped in rows of the home screen
+pub const PRESET_ROWS: [(&str, &[Preset]); 4] = [
+    ("time", &[Preset::Time(15), Preset::Time(30), Preset::Time(60), Preset::Time(120)]),
+    ("words", &[Preset::Words(10), Preset::Words(25), Preset::Words(50), Preset::Words(100)]),
+    ("quote", &[Preset::Quote(QuoteLength::Short), Preset::Quote(QuoteLength::Long)]),
+    ("", &[Preset::Custom]),
+];
+
+/// the screen shown before the first test with --home
+#[derive(Default)]
+pub struct Home {
+    row: usize,
+    col: usize,
+}
+
+impl Home {
+    pub fn get_selected(&self) -> Preset {
+        PRESET_ROWS[self.row].1[self.col]
+    }
+
+    pub fn select_up(&mut self) {
+        self.row = self.row.saturating_sub(1);
+        self.clamp_col();
+    }
+
+    pub fn select_down(&mut self) {
+        self.row = (self.row + 1).min(PRESET_ROWS.len() - 1);
+        self.clamp_col();
+    }
+
+    pub fn select_left(&mut self) {
+        self.col = self.col.saturating_sub(1);
+    }
+
+    pub fn select_right(&mut self) {
+        self.col = (self.col + 1).min(PRESET_ROWS[self.row].1.len() - 1);
+    }
+
+    // rows have different lengths
+    fn clamp_col(&mut self) {
+        self.col = self.col.min(PRESET_ROWS[self.row].1.len() - 1);
+    }
+}
@@ -0,0 +1,104 @@
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// turns the keys we get from the terminal into what the user meant to type
+pub struct Input {
+    /// compose accents typed as a separate key before the letter
+    dead_keys: bool,
+    pending_dead_key: Option<char>,
+    /// `e` matches `é`
+    lenient: bool,
+    /// pastes are IME commits and get typed
+    ime: bool,
+    /// pastes get typed but the test is flagged
+    flag_paste: bool,
+}
+
+impl Input {
+    pub fn new(args: &[String]) -> Self {
+        Self {
+            dead_keys: args.contains(&"--dead-keys".to_string()),
+            pending_dead_key: None,
+            lenient: args.contains(&"--lenient".to_string()),
+            ime: args.contains(&"--ime".to_string()),
+            flag_paste: args.contains(&"--flag-paste".to_string()),
+        }
+    }
+
+    pub fn is_ime_enabled(&self) -> bool {
+        self.ime
+    }
+
+    pub fn is_paste_allowed(&self) -> bool {
+        self.ime || self.flag_paste
+    }
+
+    pub fn is_lenient(&self) -> bool {
+        self.lenient
+    }
+
+    pub fn is_composing(&self) -> bool {
+        self.pending_dead_key.is_some()
+    }
+
+    pub fn reset(&mut self) {
+        self.pending_dead_key = None;
+    }
+
+    /// None while waiting for the letter after a dead key
+    pub fn compose(&mut self, char: char) -> Option<String> {
+        if !self.dead_keys {
+            return Some(char.to_string());
+        }
+
+        if let Some(dead_key) = self.pending_dead_key.take() {
+            // the accent itself is typed with space or by pressing the dead key twice
+            if char == ' ' || char == dead_key {
+                return Some(dead_key.to_string());
+            }
+
+            let mark = get_combining_mark(dead_key)?;
+            return Some(format!("{}{}", char, mark).nfc().collect());
+        }
+
+        if get_combining_mark(char).is_some() {
+            self.pending_dead_key = Some(char);
+            return None;
+        }
+
+        Some(char.to_string())
+    }
+
+    /// in lenient mode a letter matching `target` without accents is taken as `target`
+    pub fn resolve(&self, typed: &str, target: Option<&String>) -> String {
+        match target {
+            Some(target) if self.lenient && strip_accents(typed) == strip_accents(target) => target.clone(),
+            _ => typed.to_string(),
+        }
+    }
+}
+
+pub fn strip_accents(str: &str) -> String {
+    str.nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .collect()
+}
+
+// spacing accents that dead keys send, and the combining mark they put on the next letter
+fn get_combining_mark(dead_key: char) -> Option<char> {
+    match dead_key {
+        '´' => Some('\u{301}'),
+        '`' => Some('\u{300}'),
+        '^' => Some('\u{302}'),
+        '~' => Some('\u{303}'),
+        '¯' => Some('\u{304}'),
+        '˘' => Some('\u{306}'),
+        '˙' => Some('\u{307}'),
+        '¨' => Some('\u{308}'),
+        '˚' => Some('\u{30A}'),
+        '˝' => Some('\u{30B}'),
+        'ˇ' => Some('\u{30C}'),
+        '¸' => Some('\u{327}'),
+        '˛' => Some('\u{328}'),
+        _ => None,
+    }
+}
@@ -0,0 +1,169 @@
+use std::{fs::File, time::Duration};
+use anyhow::{Context, Result};
+use ratatui::{buffer::Buffer, layout::Rect, style::{Color, Style, Stylize}, widgets::Widget};
+use ron::de::from_reader;
+use serde::Deserialize;
+
+// physical keys, the keys we get from the terminal are assumed to be typed on qwerty
+const QWERTY_ROWS: [&str; 4] = ["`1234567890-=", "qwertyuiop[]\\", "asdfghjkl;'", "zxcvbnm,./"];
+const QWERTY_SHIFT_ROWS: [&str; 4] = ["~!@#$%^&*()_+", "QWERTYUIOP{}|", "ASDFGHJKL:\"", "ZXCVBNM<>?"];
+
+const DVORAK_ROWS: [&str; 4] = ["`1234567890[]", "',.pyfgcrl/=\\", "aoeuidhtns-", ";qjkxbmwvz"];
+const DVORAK_SHIFT_ROWS: [&str; 4] = ["~!@#$%^&*(){}", "\"<>PYFGCRL?+|", "AOEUIDHTNS_", ":QJKXBMWVZ"];
+
+const COLEMAK_ROWS: [&str; 4] = ["`1234567890-=", "qwfpgjluy;[]\\", "arstdhneio'", "zxcvbkm,./"];
+const COLEMAK_SHIFT_ROWS: [&str; 4] = ["~!@#$%^&*()_+", "QWFPGJLUY:{}|", "ARSTDHNEIO\"", "ZXCVBKM<>?"];
+
+const WORKMAN_ROWS: [&str; 4] = ["`1234567890-=", "qdrwbjfup;[]\\", "ashtgyneoi'", "zxmcvkl,./"];
+const WORKMAN_SHIFT_ROWS: [&str; 4] = ["~!@#$%^&*()_+", "QDRWBJFUP:{}|", "ASHTGYNEOI\"", "ZXMCVKL<>?"];
+
+// how far each row is shifted to the right, like on a real keyboard
+const ROW_OFFSETS: [u16; 4] = [0, 2, 3, 4];
+const KEY_WIDTH: u16 = 3;
+
+/// how long a wrongly pressed key stays red
+pub const WRONG_KEY_FLASH: Duration = Duration::from_millis(300);
+
+/// a custom layout file has the same shape as the builtin ones:
+/// `(name: "mine", rows: ["`123...", ...], shift_rows: ["~!@...", ...])`
+#[derive(Deserialize)]
+pub struct Layout {
+    pub name: String,
+    rows: Vec<String>,
+    shift_rows: Vec<String>,
+}
+
+impl Layout {
+    pub fn new(args: &[String]) -> Result<Option<Self>> {
+        let Some(idx) = args.iter().position(|i| i == "--layout") else {
+            return Ok(None);
+        };
+
+        let layout = match args.get(idx + 1).map(String::as_str) {
+            Some("qwerty") => Layout::builtin("qwerty", QWERTY_ROWS, QWERTY_SHIFT_ROWS),
+            Some("dvorak") => Layout::builtin("dvorak", DVORAK_ROWS, DVORAK_SHIFT_ROWS),
+            Some("colemak") => Layout::builtin("colemak", COLEMAK_ROWS, COLEMAK_SHIFT_ROWS),
+            Some("workman") => Layout::builtin("workman", WORKMAN_ROWS, WORKMAN_SHIFT_ROWS),
+            Some(path) if path.ends_with(".ron") => Layout::from_file(path)?,
+            _ => anyhow::bail!("add layout after --layout: qwerty, dvorak, colemak, workman or a .ron file (e.g: --layout colemak)"),
+        };
+
+        Ok(Some(layout))
+    }
+
+    pub fn qwerty() -> Self {
+        Layout::builtin("qwerty", QWERTY_ROWS, QWERTY_SHIFT_ROWS)
+    }
+
+    fn builtin(name: &str, rows: [&str; 4], shift_rows: [&str; 4]) -> Self {
+        Self {
+            name: name.to_string(),
+            rows: rows.map(String::from).to_vec(),
+            shift_rows: shift_rows.map(String::from).to_vec(),
+        }
+    }
+
+    fn from_file(path: &str) -> Result<Self> {
+        let layout: Layout = from_reader(
+            File::open(path).with_context(|| format!("layout file {} is missing", path))?
+        ).with_context(|| format!("layout file {} is incorrect", path))?;
+
+        // every physical key needs a letter
+        let fits = |rows: &Vec<String>| {
+            rows.len() == QWERTY_ROWS.len()
+                && rows.iter().zip(QWERTY_ROWS).all(|(row, qwerty)| row.chars().count() == qwerty.len())
+        };
+
+        if !fits(&layout.rows) || !fits(&layout.shift_rows) {
+            anyhow::bail!(
+                "layout file {} needs 4 rows and 4 shift_rows of {:?} keys",
+                path, QWERTY_ROWS.map(str::len)
+            );
+        }
+
+        Ok(layout)
+    }
+
+    /// the letter this layout has on the physical key that typed `char` on qwerty
+    pub fn remap(&self, char: char) -> char {
+        for (qwerty_rows, rows) in [(QWERTY_ROWS, &self.rows), (QWERTY_SHIFT_ROWS, &self.shift_rows)] {
+            for (qwerty_row, row) in qwerty_rows.iter().zip(rows) {
+                if let Some(idx) = qwerty_row.chars().position(|c| c == char) {
+                    return row.chars().nth(idx).unwrap_or(char);
+                }
+            }
+        }
+
+        char
+    }
+
+    /// (row, column) of the key that types `char`
+    fn find_key(&self, char: char) -> Option<(usize, usize)> {
+        self.rows.iter()
+            .chain(&self.shift_rows)
+            .enumerate()
+            .find_map(|(row, keys)| {
+                keys.chars().position(|c| c == char).map(|col| (row % self.rows.len(), col))
+            })
+    }
+}
+
+/// draws the layout with the key for the next letter highlighted
+/// and the key that was pressed wrongly in red
+pub struct Keyboard<'a> {
+    pub layout: &'a Layout,
+    pub next_char: Option<char>,
+    pub wrong_char: Option<char>,
+}
+
+impl Keyboard<'_> {
+    pub const WIDTH: u16 = ROW_OFFSETS[1] + 13 * KEY_WIDTH;
+    pub const HEIGHT: u16 = 5;
+}
+
+impl Widget for Keyboard<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let highlighted = self.next_char.and_then(|c| self.layout.find_key(c));
+        let wrong = self.wrong_char.and_then(|c| self.layout.find_key(c));
+        let key_style = Style::default().fg(Color::Indexed(244));
+
+        for (row, keys) in self.layout.rows.iter().enumerate() {
+            let y = area.y + row as u16;
+
+            if y >= area.bottom() {
+                return;
+            }
+
+            for (col, key) in keys.chars().enumerate() {
+                let x = area.x + ROW_OFFSETS[row] + col as u16 * KEY_WIDTH;
+
+                if x + KEY_WIDTH > area.right() {
+                    break;
+                }
+
+                let style = if wrong == Some((row, col)) {
+                    Style::default().black().on_light_red()
+                } else if highlighted == Some((row, col)) {
+                    Style::default().black().on_white()
+                } else {
+                    key_style
+                };
+
+                buf.set_string(x, y, format!(" {} ", key), style);
+            }
+        }
+
+        let y = area.y + self.layout.rows.len() as u16;
+        let x = area.x + ROW_OFFSETS[3] + 3 * KEY_WIDTH;
+        let space_width = 6 * KEY_WIDTH;
+
+        if y < area.bottom() && x + space_width <= area.right() {
+            let style = match self.next_char == Some(' ') {
+                true => Style::default().black().on_white(),
+                false => key_style,
+            };
+
+            buf.set_string(x, y, format!("[{}]", " ".repeat(space_width as usize - 2)), style);
+        }
+    }
+}
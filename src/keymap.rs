@@ -0,0 +1,342 @@
+use std::{collections::BTreeMap, fmt};
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Action {
+    Exit,
+    Restart,
+    NextTest,
+    SwapMode,
+    DeleteWord,
+    ToggleLiveStats,
+    ToggleKeyboard,
+    Start,
+    Palette,
+    Home,
+    Pause,
+    History,
+    PracticeMissed,
+    NgramStats,
+}
+
+impl Action {
+    const ALL: [Action; 14] = [
+        Action::Exit,
+        Action::Restart,
+        Action::NextTest,
+        Action::SwapMode,
+        Action::DeleteWord,
+        Action::ToggleLiveStats,
+        Action::ToggleKeyboard,
+        Action::Start,
+        Action::Palette,
+        Action::Home,
+        Action::Pause,
+        Action::History,
+        Action::PracticeMissed,
+        Action::NgramStats,
+    ];
+
+    /// the name used in the keymap in config.ron
+    fn name(&self) -> &'static str {
+        match self {
+            Action::Exit => "exit",
+            Action::Restart => "restart",
+            Action::NextTest => "next",
+            Action::SwapMode => "swap_mode",
+            Action::DeleteWord => "delete_word",
+            Action::ToggleLiveStats => "live_stats",
+            Action::ToggleKeyboard => "keyboard",
+            Action::Start => "start",
+            Action::Palette => "palette",
+            Action::Home => "home",
+            Action::Pause => "pause",
+            Action::History => "history",
+            Action::PracticeMissed => "practice_missed",
+            Action::NgramStats => "ngram_stats",
+        }
+    }
+
+    fn default_keys(&self) -> &'static [&'static str] {
+        match self {
+            Action::Exit => &["esc", "alt+q", "ctrl+c"],
+            Action::Restart => &["alt+r"],
+            Action::NextTest => &["tab", "alt+n"],
+            Action::SwapMode => &["alt+s"],
+            Action::DeleteWord => &["ctrl+w", "ctrl+h", "ctrl+backspace"],
+            Action::ToggleLiveStats => &["alt+l"],
+            Action::ToggleKeyboard => &["alt+k"],
+            Action::Start => &["enter"],
+            Action::Palette => &["ctrl+p"],
+            Action::Home => &["alt+h"],
+            Action::Pause => &["alt+p"],
+            Action::History => &["alt+y"],
+            Action::PracticeMissed => &["alt+m"],
+            Action::NgramStats => &["alt+g"],
+        }
+    }
+}
+
+/// a key with the modifiers held down, e.g: `ctrl+w`
+#[derive(Clone, Copy, PartialEq)]
+struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers = modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+
+        // shift is already in the letter (`A`, `?`) or the key (backtab)
+        if matches!(code, KeyCode::Char(_) | KeyCode::BackTab) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+
+        Self { code, modifiers }
+    }
+
+    fn parse(chord: &str) -> Option<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut key = chord;
+
+        // the key itself can be `+`, so only known prefixes are split off
+        'prefixes: loop {
+            for (prefix, modifier) in [
+                ("ctrl+", KeyModifiers::CONTROL),
+                ("alt+", KeyModifiers::ALT),
+                ("shift+", KeyModifiers::SHIFT),
+            ] {
+                if key.len() > prefix.len() && key.get(..prefix.len()).is_some_and(|p| p.eq_ignore_ascii_case(prefix)) {
+                    modifiers |= modifier;
+                    key = &key[prefix.len()..];
+                    continue 'prefixes;
+                }
+            }
+
+            break;
+        }
+
+        let shift = modifiers.contains(KeyModifiers::SHIFT);
+
+        let code = match key.to_lowercase().as_str() {
+            "esc" => KeyCode::Esc,
+            "tab" if shift => KeyCode::BackTab,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "enter" => KeyCode::Enter,
+            "backspace" => KeyCode::Backspace,
+            "space" => KeyCode::Char(' '),
+            "delete" | "del" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            f if f.len() > 1 && f.starts_with('f') => KeyCode::F(f[1..].parse().ok()?),
+            _ => {
+                let mut chars = key.chars();
+                let char = chars.next()?;
+
+                if chars.next().is_some() {
+                    return None;
+                }
+
+                match shift {
+                    true => KeyCode::Char(char.to_uppercase().next()?),
+                    false => KeyCode::Char(char),
+                }
+            }
+        };
+
+        Some(KeyChord::new(code, modifiers))
+    }
+
+    /// keys that type into the test, binding them would make the text untypeable
+    fn is_typing_key(&self) -> bool {
+        let has_mods = self.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+
+        !has_mods && matches!(self.code, KeyCode::Char(_) | KeyCode::Backspace)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "CTRL"),
+            (KeyModifiers::ALT, "ALT"),
+            (KeyModifiers::SHIFT, "SHIFT"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{} + ", name)?;
+            }
+        }
+
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "SPACE"),
+            KeyCode::Char(char) => write!(f, "{}", char),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            KeyCode::BackTab => write!(f, "SHIFT + TAB"),
+            KeyCode::PageUp => write!(f, "PAGEUP"),
+            KeyCode::PageDown => write!(f, "PAGEDOWN"),
+            code => write!(f, "{}", format!("{:?}", code).to_uppercase()),
+        }
+    }
+}
+
+/// a key event for a chord written like in the keymap, e.g: `ctrl+w`, `a`, `space`
+pub fn parse_key_event(chord: &str) -> Option<KeyEvent> {
+    KeyChord::parse(chord).map(|chord| KeyEvent::new(chord.code, chord.modifiers))
+}
+
+/// which key chords trigger which actions, the defaults
+/// can be overridden per action in the keymap in config.ron
+pub struct Keymap {
+    bindings: Vec<(KeyChord, Action)>,
+}
+
+impl Keymap {
+    pub fn new(keymap: &BTreeMap<String, Vec<String>>) -> Result<Self> {
+        let mut errors = Vec::new();
+
+        for name in keymap.keys() {
+            if !Action::ALL.iter().any(|action| action.name() == name) {
+                errors.push(format!("unknown action {:?}", name));
+            }
+        }
+
+        let mut bindings: Vec<(KeyChord, Action)> = Vec::new();
+
+        for action in Action::ALL {
+            let keys: Vec<&str> = match keymap.get(action.name()) {
+                Some(keys) => keys.iter().map(String::as_str).collect(),
+                None => action.default_keys().to_vec(),
+            };
+
+            for key in keys {
+                let Some(chord) = KeyChord::parse(key) else {
+                    errors.push(format!("{:?} bound to {} is not a key (e.g: \"alt+r\", \"ctrl+w\", \"tab\", \"f5\")", key, action.name()));
+                    continue;
+                };
+
+                if chord.is_typing_key() {
+                    errors.push(format!("{} bound to {} is needed for typing", chord, action.name()));
+                    continue;
+                }
+
+                match bindings.iter().find(|(bound, _)| *bound == chord) {
+                    Some((_, other)) if *other == action => (),
+                    Some((_, other)) => errors.push(
+                        format!("{} is bound to both {} and {}", chord, other.name(), action.name())
+                    ),
+                    None => bindings.push((chord, action)),
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            anyhow::bail!("keymap in config.ron has conflicts:\n  {}", errors.join("\n  "));
+        }
+
+        Ok(Self { bindings })
+    }
+
+    pub fn get_action(&self, key: &KeyEvent) -> Option<Action> {
+        let chord = KeyChord::new(key.code, key.modifiers);
+
+        self.bindings.iter()
+            .find(|(bound, _)| *bound == chord)
+            .map(|(_, action)| *action)
+    }
+
+    /// the keys bound to `action` as shown in hints, e.g: `TAB / ALT + n`
+    pub fn get_keys(&self, action: Action) -> Option<String> {
+        let keys: Vec<String> = self.bindings.iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(chord, _)| chord.to_string())
+            .collect();
+
+        match keys.is_empty() {
+            true => None,
+            false => Some(keys.join(" / ")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keymap(bindings: &[(&str, &[&str])]) -> Result<Keymap> {
+        Keymap::new(&bindings.iter()
+            .map(|(action, keys)| (action.to_string(), keys.iter().map(|key| key.to_string()).collect()))
+            .collect())
+    }
+
+    fn conflicts(bindings: &[(&str, &[&str])]) -> String {
+        keymap(bindings).err().map(|err| err.to_string()).unwrap_or_default()
+    }
+
+    #[test]
+    fn parses_modifiers() {
+        assert!(KeyChord::parse("ctrl+w") == Some(KeyChord::new(KeyCode::Char('w'), KeyModifiers::CONTROL)));
+        assert!(KeyChord::parse("Alt+Ctrl+r") == Some(KeyChord::new(KeyCode::Char('r'), KeyModifiers::CONTROL | KeyModifiers::ALT)));
+        assert!(KeyChord::parse("f5") == Some(KeyChord::new(KeyCode::F(5), KeyModifiers::NONE)));
+    }
+
+    #[test]
+    fn parses_plus_as_a_key() {
+        assert!(KeyChord::parse("+") == Some(KeyChord::new(KeyCode::Char('+'), KeyModifiers::NONE)));
+        assert!(KeyChord::parse("alt++") == Some(KeyChord::new(KeyCode::Char('+'), KeyModifiers::ALT)));
+    }
+
+    #[test]
+    fn shift_goes_into_the_key() {
+        assert!(KeyChord::parse("shift+a") == Some(KeyChord::new(KeyCode::Char('A'), KeyModifiers::NONE)));
+        assert!(KeyChord::parse("shift+tab") == Some(KeyChord::new(KeyCode::BackTab, KeyModifiers::NONE)));
+        assert!(KeyChord::parse("shift+tab") == KeyChord::parse("backtab"));
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert!(KeyChord::parse("ctrl+").is_none());
+        assert!(KeyChord::parse("ab").is_none());
+        assert!(KeyChord::parse("fx").is_none());
+    }
+
+    #[test]
+    fn defaults_have_no_conflicts() {
+        let keymap = keymap(&[]).unwrap();
+
+        assert!(keymap.get_action(&KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE)) == Some(Action::NextTest));
+        assert_eq!(keymap.get_keys(Action::NextTest).as_deref(), Some("TAB / ALT + n"));
+    }
+
+    #[test]
+    fn overrides_replace_the_defaults() {
+        let keymap = keymap(&[("restart", &["f5"])]).unwrap();
+
+        assert!(keymap.get_action(&KeyEvent::new(KeyCode::F(5), KeyModifiers::NONE)) == Some(Action::Restart));
+        assert!(keymap.get_action(&KeyEvent::new(KeyCode::Char('r'), KeyModifiers::ALT)).is_none());
+    }
+
+    #[test]
+    fn reports_every_conflict() {
+        let errors = conflicts(&[("restart", &["tab", "a", "hyper+x"]), ("reset", &["f1"])]);
+
+        assert!(errors.contains("unknown action \"reset\""));
+        assert!(errors.contains("TAB is bound to both restart and next"));
+        assert!(errors.contains("a bound to restart is needed for typing"));
+        assert!(errors.contains("\"hyper+x\" bound to restart is not a key"));
+    }
+
+    #[test]
+    fn same_key_twice_for_one_action_is_fine() {
+        assert!(keymap(&[("restart", &["alt+r", "Alt+r"])]).is_ok());
+    }
+}
@@ -4,10 +4,24 @@ mod app;
 mod util;
 mod ui;
 mod timer;
+mod race;
+mod input;
+mod keyboard;
+mod keymap;
+mod config;
+mod palette;
+mod home;
+mod history;
+mod export;
+mod clock;
+mod replay;
+mod ngrams;
 use app::App;
+use tui::Tui;
 use anyhow::Result;
-use std::time::Duration;
-use crossterm::event::poll;
+use std::time::{Duration, Instant};
+use util::get_arg;
+use crossterm::event::{poll, read};
 
 fn main() -> Result<()> {
     let args: Vec<String> = std::env::args().collect();
@@ -20,17 +34,64 @@ fn main() -> Result<()> {
         std::process::exit(0);
     }
 
-    let (mut app, mut tui) = App::new(&args)?;
+    let export = export::Export::new(&args)?;
+    let replay = replay::Replay::new(&args)?;
+    let tick_rate = Duration::from_millis(get_arg(&args, "--tick-rate", "100")?.unwrap_or(100));
+
+    if tick_rate.is_zero() {
+        anyhow::bail!("incorrect value: --tick-rate has to be more than 0 (e.g: --tick-rate 100)");
+    }
+
+    let mut app = App::new(&args)?;
+
+    if let Some(replay) = replay {
+        let results = replay.run(&mut app)?;
+        return export.unwrap_or_default().print(&results);
+    }
+
+    let mut tui = Tui::enter()?;
+    // the terminal has to be restored before an error is printed
+    let res = run(&mut app, &mut tui, tick_rate);
+    tui.exit()?;
+    res?;
+
+    if let Some(export) = export {
+        export.print(&app.session_results)?;
+    }
+
+    Ok(())
+}
+
+fn run(app: &mut App, tui: &mut Tui, tick_rate: Duration) -> Result<()> {
+    let mut redraw = true;
+    let mut last_tick = Instant::now();
 
     while !app.should_exit() {
-        tui.draw(&mut app)?;
+        if redraw {
+            tui.draw(app)?;
+            redraw = false;
+        }
+
+        // wake up right when the time runs out, not on the next tick
+        let time_left = app.timer.get_time_left();
+        let timeout = tick_rate
+            .saturating_sub(last_tick.elapsed())
+            .min(time_left.unwrap_or(Duration::MAX));
 
-        if poll(Duration::from_secs(2))? {
-            app.update(&mut tui)?;
+        if poll(timeout)? {
+            app.update(read()?)?;
+            app.tick()?;
+            redraw = true;
+        }
+
+        let timed_out = time_left.is_some() && app.timer.is_out_of_time();
+
+        if timed_out || last_tick.elapsed() >= tick_rate {
+            redraw |= app.tick()?;
+            last_tick = Instant::now();
         }
     }
 
-    tui.exit()?;
     Ok(())
 }
 
@@ -38,10 +99,46 @@ fn print_help() {
     println!(
         "\
         tt-rs - tui typing test \n\n\
+        usage: tt-rs [OPTIONS] \
+        \n       tt-rs race (--host <PORT> | --join <ADDR>) [--name <NAME>] [OPTIONS] \n\n\
         -t <TIME>     Specify time for the timer in secs \
         \n-w <NUM>      Specify the number of words in the test \
+        \n--home        Start on a home screen with presets and personal bests \
         \n-q            Test contains quotes instead of words \
+        \n--list <NAME>  Take the words from ~/.config/tt-rs/lists/NAME.ron \
+        \n               (trouble is made from your most missed and slowest words) \
         \n-d            Each time you make an mistake the test will restart \
+        \n-e <MODE>     What happens on a mistake: \
+        \n                letter - the wrong key is rejected \
+        \n                word   - a word with mistakes can't be left \
+        \n                retry  - the test restarts with the same text \
+        \n                death  - the test restarts with a new text (same as -d) \
+        \n-c <SECS>     Wait for ENTER and count down SECS before the test starts \
+        \n--dead-keys   Compose accents typed with a separate key before the letter (´ + e = é) \
+        \n--lenient     Letters without accents match letters with accents (e matches é) \
+        \n--ime         Type text committed by an input method (sent as a paste) \
+        \n--flag-paste  Type pasted text but flag the test (pastes are ignored by default) \
+        \n--layout <LAYOUT>  Practice a layout (colemak, dvorak, workman or a .ron file) \
+        \n                   on a qwerty keyboard, with the next key shown on screen \
+        \n-k            Show an on-screen keyboard with the next key highlighted \
+        \n--live <POS>  Show live WPM, accuracy and progress at the top or bottom of the text \
+        \n--min-acc <PCT>  Fail the test when the accuracy drops below PCT \
+        \n--min-wpm <WPM>  Fail the test when the speed drops below WPM \
+        \n--grace <SECS>   Don't fail the test in the first SECS (default: 5) \
+        \n--tick-rate <MS>  How often the timer and live stats are redrawn (default: 100) \
+        \n--json        Print the result of the last test as JSON to stdout on exit \
+        \n--csv         Print the result of the last test as CSV to stdout on exit \
+        \n--all         With --json or --csv print every test of the session \
+        \n--replay-input <FILE> --headless  Feed the key events in FILE (one JSON object per line) \
+        \n                   through the test without a terminal and print the stats (JSON by default) \
+        \n\nrace: \
+        \n--host <PORT> Host a race on PORT, the host picks the text \
+        \n--join <ADDR> Join a race hosted on ADDR (e.g: 192.168.1.5:4000) \
+        \n--name <NAME> Name shown to the other racers (default: $USER) \
+        \n\nCTRL + p opens the settings palette, ALT + h the home screen, ALT + p pauses the test, ALT + y shows the history \
+        \nALT + m practices the words missed in the last test, ALT + g shows the slowest bigrams and trigrams \
+        \nclick a word to practice from it, the results have next, retry, practice missed and history buttons \
+        \nkeys can be rebound in the keymap in ~/.config/tt-rs/config.ron \
         "
     );
 }
@@ -0,0 +1,71 @@
+use std::{collections::BTreeMap, time::Duration};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+use crate::util::{load_ron, save_ron};
+
+/// typed fewer times than this says more about luck than about the fingers
+const MIN_NGRAM_COUNT: u32 = 3;
+
+/// the time between the keys of a bigram or trigram in every test
+#[derive(Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(default)]
+pub struct Latency {
+    /// in ms
+    pub total: f64,
+    pub count: u32,
+}
+
+impl Latency {
+    /// in ms per key
+    pub fn get_average(&self) -> f64 {
+        self.total / self.count.max(1) as f64
+    }
+}
+
+/// latencies of every bigram and trigram typed right, kept in ~/.config/tt-rs/ngrams.ron
+#[derive(Default)]
+pub struct Ngrams {
+    latencies: BTreeMap<String, Latency>,
+    /// only loaded latencies are saved
+    persist: bool,
+}
+
+impl Ngrams {
+    pub fn load() -> Result<Self> {
+        Ok(Self { latencies: load_ron("ngrams.ron", "ngrams file")?, persist: true })
+    }
+
+    fn save(&self) -> Result<()> {
+        save_ron("ngrams.ron", "ngrams file", &self.latencies)
+    }
+
+    pub fn add(&mut self, latencies: &[(String, Duration)]) -> Result<()> {
+        if latencies.is_empty() {
+            return Ok(())
+        }
+
+        for (ngram, latency) in latencies {
+            let entry = self.latencies.entry(ngram.clone()).or_default();
+            entry.total += latency.as_secs_f64() * 1000.0;
+            entry.count += 1;
+        }
+
+        match self.persist {
+            true => self.save(),
+            false => Ok(()),
+        }
+    }
+
+    /// the slowest bigrams (`len` 2) or trigrams (`len` 3), the slowest first
+    pub fn get_slowest(&self, len: usize, count: usize) -> Vec<(&str, Latency)> {
+        let mut slowest: Vec<(&str, Latency)> = self.latencies.iter()
+            .filter(|(ngram, latency)| ngram.graphemes(true).count() == len && latency.count >= MIN_NGRAM_COUNT)
+            .map(|(ngram, latency)| (ngram.as_str(), *latency))
+            .collect();
+
+        slowest.sort_by(|a, b| b.1.get_average().total_cmp(&a.1.get_average()));
+        slowest.truncate(count);
+        slowest
+    }
+}
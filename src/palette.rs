@@ -0,0 +1,113 @@
+/// the settings and actions listed in the palette, in order
+#[derive(Clone, Copy, PartialEq)]
+pub enum PaletteItem {
+    WordCount,
+    Time,
+    Source,
+    WordList,
+    Mode,
+    ErrorMode,
+    Restart,
+    NextTest,
+    Exit,
+}
+
+impl PaletteItem {
+    pub const ALL: [PaletteItem; 9] = [
+        PaletteItem::WordCount,
+        PaletteItem::Time,
+        PaletteItem::Source,
+        PaletteItem::WordList,
+        PaletteItem::Mode,
+        PaletteItem::ErrorMode,
+        PaletteItem::Restart,
+        PaletteItem::NextTest,
+        PaletteItem::Exit,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PaletteItem::WordCount => "words",
+            PaletteItem::Time => "time",
+            PaletteItem::Source => "text",
+            PaletteItem::WordList => "word list",
+            PaletteItem::Mode => "mode",
+            PaletteItem::ErrorMode => "on error",
+            PaletteItem::Restart => "restart test",
+            PaletteItem::NextTest => "next test",
+            PaletteItem::Exit => "quit",
+        }
+    }
+
+    /// settings are changed in place, actions close the palette
+    pub fn is_action(&self) -> bool {
+        matches!(self, PaletteItem::Restart | PaletteItem::NextTest | PaletteItem::Exit)
+    }
+}
+
+pub const WORD_COUNTS: [usize; 5] = [10, 25, 50, 100, 200];
+/// in secs, None is no time limit
+pub const TIME_LIMITS: [Option<u64>; 5] = [None, Some(15), Some(30), Some(60), Some(120)];
+
+#[derive(Default)]
+pub struct Palette {
+    selected: usize,
+    /// the timer was paused by opening the palette, so closing it resumes it
+    pub paused_timer: bool,
+}
+
+impl Palette {
+    pub fn get_selected(&self) -> PaletteItem {
+        PaletteItem::ALL[self.selected]
+    }
+
+    pub fn select_prev(&mut self) {
+        self.selected = self.selected.checked_sub(1).unwrap_or(PaletteItem::ALL.len() - 1);
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1) % PaletteItem::ALL.len();
+    }
+}
+
+/// the option after (or before) `curr`, wrapping around,
+/// the first one if `curr` isn't an option (e.g: `-w 42`)
+pub fn cycle<T: PartialEq + Clone>(options: &[T], curr: &T, forward: bool) -> T {
+    let idx = match options.iter().position(|option| option == curr) {
+        Some(idx) if forward => (idx + 1) % options.len(),
+        Some(idx) => idx.checked_sub(1).unwrap_or(options.len() - 1),
+        None => 0,
+    };
+
+    options[idx].clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycles_forward_and_back() {
+        assert_eq!(cycle(&WORD_COUNTS, &25, true), 50);
+        assert_eq!(cycle(&WORD_COUNTS, &25, false), 10);
+    }
+
+    #[test]
+    fn wraps_around() {
+        assert_eq!(cycle(&WORD_COUNTS, &200, true), 10);
+        assert_eq!(cycle(&WORD_COUNTS, &10, false), 200);
+        assert_eq!(cycle(&TIME_LIMITS, &Some(120), true), None);
+    }
+
+    #[test]
+    fn unknown_values_go_to_the_first_option() {
+        assert_eq!(cycle(&WORD_COUNTS, &42, true), 10);
+        assert_eq!(cycle(&WORD_COUNTS, &42, false), 10);
+    }
+
+    #[test]
+    fn one_option_stays() {
+        assert_eq!(cycle(&["a"], &"a", true), "a");
+        assert_eq!(cycle(&["a"], &"a", false), "a");
+    }
+}
@@ -0,0 +1,320 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    sync::{mpsc::{channel, Receiver, Sender}, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use crate::util::to_graphemes;
+
+pub const RACE_COUNTDOWN: Duration = Duration::from_secs(3);
+/// a racer that can't take a message this fast is dropped, instead of freezing everyone
+const WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+
+// every message is sent as a single line of ron
+#[derive(Serialize, Deserialize, Clone)]
+pub enum Message {
+    Join { name: String },
+    Welcome { id: u32 },
+    Text { text: String },
+    Start { countdown_ms: u64 },
+    Progress { progress: f64, wpm: f64 },
+    Players { players: Vec<Player> },
+    Leave,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Player {
+    pub id: u32,
+    pub name: String,
+    pub progress: f64,
+    pub wpm: f64,
+}
+
+/// what the app has to act on after polling the network
+pub enum RaceEvent {
+    Text(Vec<String>),
+    Start(Duration),
+}
+
+type Clients = Arc<Mutex<Vec<(u32, TcpStream)>>>;
+
+enum Role {
+    Host { port: u16, clients: Clients, text: String },
+    Client { addr: String, stream: TcpStream, connected: bool },
+}
+
+pub struct Race {
+    id: u32,
+    role: Role,
+    rx: Receiver<(u32, Message)>,
+    players: Vec<Player>,
+    // last (progress, wpm) we told the others about
+    sent_progress: (f64, f64),
+    in_lobby: bool,
+}
+
+impl Race {
+    pub fn new(args: &[String]) -> Result<Self> {
+        let name = match args.iter().position(|i| i == "--name") {
+            Some(idx) => args.get(idx + 1)
+                .with_context(|| "add name after --name (e.g: --name bob)")?
+                .clone(),
+            None => std::env::var("USER").unwrap_or("player".to_string()),
+        };
+
+        if let Some(idx) = args.iter().position(|i| i == "--host") {
+            let port = args.get(idx + 1)
+                .with_context(|| "add port after --host (e.g: --host 4000)")?
+                .parse()
+                .with_context(|| "incorrect port: add port after --host (e.g: --host 4000)")?;
+
+            return Race::host(port, name);
+        }
+
+        if let Some(idx) = args.iter().position(|i| i == "--join") {
+            let addr = args.get(idx + 1)
+                .with_context(|| "add address after --join (e.g: --join 127.0.0.1:4000)")?;
+
+            return Race::join(addr, name);
+        }
+
+        anyhow::bail!("race needs either --host <PORT> or --join <ADDR>")
+    }
+
+    fn host(port: u16, name: String) -> Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))
+            .with_context(|| format!("could not listen on port {}", port))?;
+
+        let (tx, rx) = channel();
+        let clients: Clients = Arc::new(Mutex::new(Vec::new()));
+
+        let accept_clients = clients.clone();
+        thread::spawn(move || {
+            let mut next_id = 1;
+
+            for stream in listener.incoming().flatten() {
+                let _ = stream.set_nodelay(true);
+                let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+
+                if let Ok(reader) = stream.try_clone() {
+                    accept_clients.lock().unwrap().push((next_id, stream));
+                    spawn_reader(reader, next_id, tx.clone());
+
+                    next_id += 1;
+                }
+            }
+        });
+
+        Ok(Self {
+            id: 0,
+            role: Role::Host { port, clients, text: String::new() },
+            rx,
+            players: vec![Player { id: 0, name, progress: 0.0, wpm: 0.0 }],
+            sent_progress: (0.0, 0.0),
+            in_lobby: true,
+        })
+    }
+
+    fn join(addr: &str, name: String) -> Result<Self> {
+        let mut stream = TcpStream::connect(addr)
+            .with_context(|| format!("could not connect to {}", addr))?;
+        stream.set_nodelay(true)?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        writeln!(stream, "{}", ron::to_string(&Message::Join { name })?)?;
+
+        let (tx, rx) = channel();
+        spawn_reader(stream.try_clone()?, 0, tx);
+
+        Ok(Self {
+            id: 0,
+            role: Role::Client { addr: addr.to_string(), stream, connected: true },
+            rx,
+            players: Vec::new(),
+            sent_progress: (0.0, 0.0),
+            in_lobby: true,
+        })
+    }
+
+    pub fn is_host(&self) -> bool {
+        matches!(self.role, Role::Host { .. })
+    }
+
+    pub fn is_in_lobby(&self) -> bool {
+        self.in_lobby
+    }
+
+    pub fn get_players(&self) -> &Vec<Player> {
+        &self.players
+    }
+
+    pub fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    /// short description of the connection for the lobby screen
+    pub fn get_location(&self) -> String {
+        match &self.role {
+            Role::Host { port, .. } => format!("hosting race on port {}", port),
+            Role::Client { addr, connected: true, .. } => format!("connected to {}", addr),
+            Role::Client { addr, connected: false, .. } => format!("lost connection to {}", addr),
+        }
+    }
+
+    /// host only, sends the new text and the countdown to every player
+    pub fn start(&mut self, text: &[String], countdown: Duration) {
+        if let Role::Host { text: host_text, .. } = &mut self.role {
+            *host_text = text.concat();
+        }
+
+        self.broadcast(&Message::Text { text: text.concat() });
+        self.broadcast(&Message::Start { countdown_ms: countdown.as_millis() as u64 });
+
+        self.players.iter_mut().for_each(|p| { p.progress = 0.0; p.wpm = 0.0 });
+        self.in_lobby = false;
+    }
+
+    pub fn send_progress(&mut self, progress: f64, wpm: f64) {
+        if self.sent_progress == (progress, wpm) {
+            return;
+        }
+        self.sent_progress = (progress, wpm);
+
+        let id = self.id;
+        if let Some(player) = self.players.iter_mut().find(|p| p.id == id) {
+            player.progress = progress;
+            player.wpm = wpm;
+        }
+
+        match self.is_host() {
+            true => self.broadcast_players(),
+            false => self.send(&Message::Progress { progress, wpm }),
+        }
+    }
+
+    pub fn poll(&mut self) -> Vec<RaceEvent> {
+        let mut events = Vec::new();
+
+        while let Ok((id, message)) = self.rx.try_recv() {
+            match self.is_host() {
+                true => self.handle_host_message(id, message),
+                false => self.handle_client_message(message, &mut events),
+            }
+        }
+
+        events
+    }
+
+    fn handle_host_message(&mut self, id: u32, message: Message) {
+        match message {
+            Message::Join { name } => {
+                self.players.push(Player { id, name, progress: 0.0, wpm: 0.0 });
+                self.send_to(id, &Message::Welcome { id });
+
+                if let Role::Host { text, .. } = &self.role {
+                    if !text.is_empty() {
+                        let text = text.clone();
+                        self.send_to(id, &Message::Text { text });
+                    }
+                }
+            },
+            Message::Progress { progress, wpm } => {
+                if let Some(player) = self.players.iter_mut().find(|p| p.id == id) {
+                    player.progress = progress;
+                    player.wpm = wpm;
+                }
+            },
+            Message::Leave => {
+                if let Role::Host { clients, .. } = &self.role {
+                    clients.lock().unwrap().retain(|(client_id, _)| *client_id != id);
+                }
+
+                self.players.retain(|p| p.id != id);
+            },
+            _ => return
+        }
+
+        self.broadcast_players();
+    }
+
+    fn handle_client_message(&mut self, message: Message, events: &mut Vec<RaceEvent>) {
+        match message {
+            Message::Welcome { id } => self.id = id,
+            Message::Text { text } => events.push(RaceEvent::Text(to_graphemes(&text))),
+            Message::Start { countdown_ms } => {
+                self.in_lobby = false;
+                events.push(RaceEvent::Start(Duration::from_millis(countdown_ms)));
+            },
+            Message::Players { players } => self.players = players,
+            Message::Leave => self.disconnect(),
+            _ => ()
+        }
+    }
+
+    fn broadcast_players(&mut self) {
+        self.broadcast(&Message::Players { players: self.players.clone() });
+    }
+
+    fn broadcast(&mut self, message: &Message) {
+        self.send_to_each(message, |_| true);
+    }
+
+    fn send_to(&mut self, id: u32, message: &Message) {
+        self.send_to_each(message, |client_id| client_id == id);
+    }
+
+    // racers whose stream is broken or stalled are dropped together with their player
+    fn send_to_each(&mut self, message: &Message, to: impl Fn(u32) -> bool) {
+        let Role::Host { clients, .. } = &self.role else { return };
+        let line = ron::to_string(message).unwrap_or_default();
+        let mut dropped = Vec::new();
+
+        clients.lock().unwrap().retain_mut(|(id, stream)| {
+            if !to(*id) || writeln!(stream, "{}", line).is_ok() {
+                return true;
+            }
+
+            dropped.push(*id);
+            false
+        });
+
+        self.players.retain(|p| !dropped.contains(&p.id));
+    }
+
+    // a host that is gone or stalled ends the race for us, but not the test we are typing
+    fn send(&mut self, message: &Message) {
+        let Role::Client { stream, connected: true, .. } = &mut self.role else { return };
+
+        if writeln!(stream, "{}", ron::to_string(message).unwrap_or_default()).is_err() {
+            self.disconnect();
+        }
+    }
+
+    // the host is gone, keep only ourself around
+    fn disconnect(&mut self) {
+        if let Role::Client { stream, connected, .. } = &mut self.role {
+            let _ = stream.shutdown(Shutdown::Both);
+            *connected = false;
+        }
+
+        self.players.retain(|p| p.id == self.id);
+    }
+}
+
+fn spawn_reader(stream: TcpStream, id: u32, tx: Sender<(u32, Message)>) {
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else { break };
+
+            if let Ok(message) = ron::from_str(&line) {
+                if tx.send((id, message)).is_err() {
+                    return;
+                }
+            }
+        }
+
+        let _ = tx.send((id, Message::Leave));
+    });
+}
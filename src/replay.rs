@@ -0,0 +1,110 @@
+use std::{fs::File, io::{BufRead, BufReader}, time::{Duration, Instant}};
+use anyhow::{Context, Result};
+use crossterm::event::Event;
+use serde::Deserialize;
+use crate::{app::App, clock, history::TestResult, keymap::parse_key_event, util::to_graphemes};
+
+/// one line of the events file, e.g:
+/// `{"text": "the quick fox"}`, `{"time": 120, "key": "t"}`, `{"time": 900, "key": "ctrl+w"}`, `{"time": 1000, "paste": "日本"}`
+#[derive(Deserialize)]
+struct ReplayLine {
+    /// ms since the start of the replay
+    #[serde(default)]
+    time: u64,
+    key: Option<String>,
+    paste: Option<String>,
+    /// replaces the random text so the results can be compared between runs
+    text: Option<String>,
+}
+
+enum ReplayEvent {
+    Event(Event),
+    Text(Vec<String>),
+}
+
+/// key events from a file fed through the same path as the terminal's, with no terminal
+pub struct Replay {
+    events: Vec<(Duration, ReplayEvent)>,
+}
+
+impl Replay {
+    pub fn new(args: &[String]) -> Result<Option<Self>> {
+        let headless = args.contains(&"--headless".to_string());
+        let Some(idx) = args.iter().position(|i| i == "--replay-input") else {
+            if headless {
+                anyhow::bail!("--headless needs events to replay (e.g: --replay-input events.jsonl --headless)");
+            }
+
+            return Ok(None);
+        };
+
+        if !headless {
+            anyhow::bail!("--replay-input only works with --headless (e.g: --replay-input events.jsonl --headless)");
+        }
+
+        if args.get(1).is_some_and(|arg| arg == "race") {
+            anyhow::bail!("races can't be replayed");
+        }
+
+        let path = args.get(idx + 1)
+            .with_context(|| "add events file after --replay-input (e.g: --replay-input events.jsonl)")?;
+
+        let file = File::open(path).with_context(|| format!("events file {} is missing", path))?;
+        let mut events = Vec::new();
+
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let parsed: ReplayLine = serde_json::from_str(&line)
+                .with_context(|| format!("{}:{} is incorrect", path, i + 1))?;
+
+            let event = match (parsed.key, parsed.paste, parsed.text) {
+                (Some(key), None, None) => ReplayEvent::Event(Event::Key(
+                    parse_key_event(&key)
+                        .with_context(|| format!("{}:{} {:?} is not a key (e.g: \"a\", \"space\", \"backspace\", \"ctrl+w\")", path, i + 1, key))?
+                )),
+                (None, Some(paste), None) => ReplayEvent::Event(Event::Paste(paste)),
+                (None, None, Some(text)) => ReplayEvent::Text(to_graphemes(&text)),
+                _ => anyhow::bail!("{}:{} needs one of key, paste or text", path, i + 1),
+            };
+
+            events.push((Duration::from_millis(parsed.time), event));
+        }
+
+        Ok(Some(Self { events }))
+    }
+
+    /// the results of every finished test, and of the last one even if it wasn't finished
+    pub fn run(self, app: &mut App) -> Result<Vec<TestResult>> {
+        let start = Instant::now();
+        clock::set_now(start);
+
+        for (time, event) in self.events {
+            clock::set_now(start + time);
+            app.tick()?;
+
+            match event {
+                ReplayEvent::Event(event) => app.update(event)?,
+                ReplayEvent::Text(text) => app.set_target_text(text)?,
+            }
+
+            app.tick()?;
+
+            if app.should_exit() {
+                break;
+            }
+        }
+
+        let mut results = app.session_results.clone();
+
+        if app.timer.is_started() && !app.is_test_over() {
+            results.push(app.get_result());
+        }
+
+        Ok(results)
+    }
+}
@@ -1,15 +1,23 @@
 use std::time::{Duration, Instant};
 use anyhow::{Context, Result};
+use crate::clock;
 
+const DEFAULT_TIME: Duration = Duration::from_secs(1200);
 
 pub struct Timer {
     start_time: Option<Instant>,
     end_time: Option<Instant>,
+    paused_at: Option<Instant>,
+    /// total time spent paused before `paused_at`
+    paused_for: Duration,
     timer_time: Duration,
+    /// without -t the timer only runs out after DEFAULT_TIME
+    limited: bool,
+    countdown_time: Option<Duration>,
 }
 
 impl Timer {
-    pub fn new(args: &Vec<String>) -> Result<Self> {
+    pub fn new(args: &[String]) -> Result<Self> {
         let timer_time = {
             if let Some(time) = args.iter().position(|i| i == &"-t".to_string()) {
                 Duration::from_secs(
@@ -19,26 +27,44 @@ impl Timer {
                     .with_context(|| "incorrect duration: add time after -t in secs (e.g: -t 30)")?
                 )
             } else {
-                Duration::from_secs(1200) 
+                DEFAULT_TIME
             }
         };
 
+        // with a countdown the test waits for ENTER instead of starting on the first key
+        let countdown_time = {
+            if let Some(time) = args.iter().position(|i| i == &"-c".to_string()) {
+                Some(Duration::from_secs(
+                    args.get(time + 1)
+                    .with_context(|| "add countdown after -c in secs (e.g: -c 3)")?
+                    .parse()
+                    .with_context(|| "incorrect countdown: add countdown after -c in secs (e.g: -c 3)")?
+                ))
+            } else {
+                None
+            }
+        };
 
         Ok(
             Self {
                 start_time: None,
                 end_time: None,
-                timer_time
+                paused_at: None,
+                paused_for: Duration::ZERO,
+                timer_time,
+                limited: args.contains(&"-t".to_string()),
+                countdown_time,
             }
         )
     }
 
     pub fn start(&mut self) {
-        self.start_time = Some(Instant::now());
+        self.start_time = Some(clock::now());
     }
 
     pub fn stop(&mut self) {
-        self.end_time = Some(Instant::now());
+        self.resume();
+        self.end_time = Some(clock::now());
     }
 
     pub fn is_started(&self) -> bool {
@@ -49,9 +75,34 @@ impl Timer {
         self.end_time.is_some()
     }
 
+    /// only a running timer can be paused
+    pub fn pause(&mut self) {
+        if self.start_time.is_some() && self.end_time.is_none() && self.paused_at.is_none() && !self.is_out_of_time() {
+            self.paused_at = Some(clock::now());
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.paused_for += clock::elapsed(paused_at);
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    /// time since `start_time` until now (or the end) without the time spent paused
+    fn get_active_time(&self, start_time: Instant) -> Duration {
+        let now = self.end_time.unwrap_or_else(clock::now);
+        let paused = self.paused_for + self.paused_at.map_or(Duration::ZERO, |paused_at| now.saturating_duration_since(paused_at));
+
+        now.saturating_duration_since(start_time).saturating_sub(paused)
+    }
+
     /// can fail if timer not started
     pub fn get_elapsed(&self) -> Duration {
-        self.start_time.unwrap().elapsed()
+        self.get_active_time(self.start_time.unwrap())
     }
 
     pub fn get_remaining(&self) -> u64 {
@@ -62,23 +113,160 @@ impl Timer {
 
     pub fn is_out_of_time(&self) -> bool {
         if let Some(st) = self.start_time {
-            return st.elapsed() >= self.timer_time
+            return self.get_active_time(st) >= self.timer_time
         }
 
         false
     }
 
+    /// how long until the time runs out, None if the timer isn't running
+    pub fn get_time_left(&self) -> Option<Duration> {
+        match (self.start_time, self.end_time) {
+            (Some(start_time), None) if !self.is_out_of_time() => {
+                Some(self.timer_time.saturating_sub(self.get_active_time(start_time)))
+            },
+            _ => None,
+        }
+    }
+
+    pub fn get_time_limit(&self) -> Option<Duration> {
+        self.limited.then_some(self.timer_time)
+    }
+
+    pub fn set_time_limit(&mut self, limit: Option<Duration>) {
+        self.limited = limit.is_some();
+        self.timer_time = limit.unwrap_or(DEFAULT_TIME);
+    }
+
+    pub fn get_countdown_time(&self) -> Option<Duration> {
+        self.countdown_time
+    }
+
     pub fn reset(&mut self) {
         self.start_time = None;
         self.end_time = None;
+        self.paused_at = None;
+        self.paused_for = Duration::ZERO;
     }
 
     pub fn get_time(&self) -> Duration {
-        if let Some(end_time) = self.end_time {
-            return end_time - self.start_time.unwrap();
+        if self.end_time.is_some() {
+            return self.get_active_time(self.start_time.unwrap());
         }
 
-        // if we don't have end_time that means that we ran out of time
-        self.timer_time
+        // if we don't have end_time that means that we are still typing or ran out of time
+        if let Some(start_time) = self.start_time {
+            return self.get_active_time(start_time).min(self.timer_time);
+        }
+
+        Duration::ZERO
+    }
+}
+
+pub struct Countdown {
+    end_time: Instant,
+}
+
+impl Countdown {
+    pub fn new(duration: Duration) -> Self {
+        Self { end_time: clock::now() + duration }
+    }
+
+    pub fn is_done(&self) -> bool {
+        clock::now() >= self.end_time
+    }
+
+    /// remaining secs rounded up so the countdown goes 3, 2, 1
+    pub fn get_remaining(&self) -> u64 {
+        let remaining = self.end_time.saturating_duration_since(clock::now());
+
+        remaining.as_millis().div_ceil(1000) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEC: Duration = Duration::from_secs(1);
+
+    fn timer(args: &[&str]) -> Timer {
+        Timer::new(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>()).unwrap()
+    }
+
+    #[test]
+    fn paused_time_is_left_out() {
+        let start = Instant::now();
+        clock::set_now(start);
+
+        let mut timer = timer(&[]);
+        timer.start();
+
+        clock::set_now(start + 2 * SEC);
+        timer.pause();
+        clock::set_now(start + 7 * SEC);
+        assert_eq!(timer.get_time(), 2 * SEC);
+
+        timer.resume();
+        clock::set_now(start + 8 * SEC);
+        timer.stop();
+        clock::set_now(start + 20 * SEC);
+
+        assert_eq!(timer.get_time(), 3 * SEC);
+    }
+
+    #[test]
+    fn stopping_while_paused_ends_at_the_pause() {
+        let start = Instant::now();
+        clock::set_now(start);
+
+        let mut timer = timer(&[]);
+        timer.start();
+
+        clock::set_now(start + SEC);
+        timer.pause();
+        clock::set_now(start + 4 * SEC);
+        timer.stop();
+
+        assert!(!timer.is_paused());
+        assert_eq!(timer.get_time(), SEC);
+    }
+
+    #[test]
+    fn paused_time_doesnt_run_out() {
+        let start = Instant::now();
+        clock::set_now(start);
+
+        let mut timer = timer(&["-t", "5"]);
+        timer.start();
+
+        clock::set_now(start + 4 * SEC);
+        timer.pause();
+        clock::set_now(start + 10 * SEC);
+        assert!(!timer.is_out_of_time());
+        assert_eq!(timer.get_time_left(), Some(SEC));
+
+        timer.resume();
+        clock::set_now(start + 10 * SEC + SEC / 2);
+        assert!(!timer.is_out_of_time());
+
+        clock::set_now(start + 11 * SEC);
+        assert!(timer.is_out_of_time());
+        assert_eq!(timer.get_time(), 5 * SEC);
+    }
+
+    #[test]
+    fn out_of_time_can_not_be_paused() {
+        let start = Instant::now();
+        clock::set_now(start);
+
+        let mut timer = timer(&["-t", "5"]);
+        timer.start();
+
+        clock::set_now(start + 6 * SEC);
+        timer.pause();
+
+        assert!(!timer.is_paused());
+        assert_eq!(timer.get_time(), 5 * SEC);
     }
 }
@@ -1,6 +1,6 @@
 use crossterm::{
     cursor::SetCursorStyle,
-    event::{DisableMouseCapture, EnableMouseCapture},
+    event::{DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste, EnableFocusChange, EnableMouseCapture},
     execute, 
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen}
 };
@@ -16,7 +16,9 @@ pub struct Tui {
 impl Tui {
     pub fn enter() -> Result<Self> {
         enable_raw_mode()?;
-        execute!(stderr(), EnterAlternateScreen, EnableMouseCapture, SetCursorStyle::SteadyBar)?;
+        // pastes come as one event instead of keys so they can't be typed by accident
+        execute!(stderr(), EnterAlternateScreen, EnableMouseCapture, EnableBracketedPaste, EnableFocusChange, SetCursorStyle::SteadyBar)?;
+
         let mut term = Terminal::new(CrosstermBackend::new(stderr()))?;
         term.clear()?;
 
@@ -35,7 +37,7 @@ impl Tui {
     }
 
     pub fn leave_tui() -> Result<()> {
-        execute!(stderr(), LeaveAlternateScreen, DisableMouseCapture, SetCursorStyle::DefaultUserShape)?;
+        execute!(stderr(), LeaveAlternateScreen, DisableMouseCapture, DisableBracketedPaste, DisableFocusChange, SetCursorStyle::DefaultUserShape)?;
         disable_raw_mode()?;
         Ok(())
     }
@@ -1,21 +1,82 @@
 use std::time::Duration;
-use crate::app::App;
+use crate::app::{get_line_starts, get_xy_wrapped, App, LiveStatsPosition};
+use crate::input::strip_accents;
+use crate::keyboard::{Keyboard, Layout};
+use crate::keymap::Action;
+use crate::palette::PaletteItem;
+use crate::home::PRESET_ROWS;
+use crate::history::join_flags;
+use crate::ngrams::Latency;
+use unicode_width::UnicodeWidthStr;
 use ratatui::prelude::*;
 use ratatui::style::Stylize;
 use ratatui::widgets::*;
+use ratatui::widgets::block::{Position, Title};
 
 pub fn render(app: &mut App, frame: &mut Frame) {
     app.update_rect(frame.size());
 
+    render_screen(app, frame);
+
+    if app.palette.is_some() {
+        render_palette(app, frame);
+    }
+}
+
+fn render_screen(app: &mut App, frame: &mut Frame) {
+    if app.is_in_race_lobby() {
+        render_lobby(app, frame);
+        return;
+    }
+
+    if app.show_ngrams {
+        render_ngrams(app, frame);
+        return;
+    }
+
+    if app.history_view.is_some() {
+        render_history(app, frame);
+        return;
+    }
+
+    if app.home.is_some() {
+        render_home(app, frame);
+        return;
+    }
+
+    if app.race.is_some() {
+        render_racers(app, frame);
+    }
+
+    if app.failed.is_some() {
+        render_failed(app, frame);
+        return;
+    }
+
     if app.is_finished_typing() || app.timer.is_out_of_time() {
         render_stats(app, frame);
         return;
     }
 
+    if app.countdown.is_some() {
+        render_countdown(app, frame);
+        return;
+    }
+
+    if app.timer.is_paused() {
+        render_paused(app, frame);
+        return;
+    }
+
     render_text(app, frame);
 
-    if app.timer.is_started() {
+    render_keyboard(app, frame);
+
+    if app.is_waiting_for_start() {
+        render_start_hint(app, frame);
+    } else if app.timer.is_started() {
         render_timer(app, frame);
+        render_live_stats(app, frame);
     }
 }
 
@@ -25,31 +86,45 @@ pub fn render_text(app: &mut App, frame: &mut Frame) {
     match app.is_in_scroller_mode() {
         true => {
             app.adjust_filler_txt();
-            render_scroller(app, frame, gen_chars(&app.target_text, &app.curr_text))
+            render_scroller(app, frame, gen_chars(app))
         },
         false => {
-            render_wrapped(app, frame, gen_chars(&app.target_text, &app.curr_text))
+            render_wrapped(app, frame, gen_chars(app))
         }
     }
 }
 
-fn gen_chars<'a>(target_text: &Vec<char>, curr_text: &Vec<char>) -> Vec<Span<'a>> {
-    target_text.iter().enumerate().map(|(i, &target_c)| {
-        if let Some(c) = curr_text.get(i) {
-            if *c == target_c {
-                target_c.to_string().white()
+fn gen_chars<'a>(app: &App) -> Vec<Span<'a>> {
+    let mut chars = Vec::with_capacity(app.target_text.len());
+
+    for (i, target_c) in app.target_text.iter().enumerate() {
+        if let Some(extra) = app.extra_text.get(&i) {
+            chars.extend(extra.iter().map(|c| c.clone().red()));
+        }
+
+        chars.push(
+            if let Some(c) = app.curr_text.get(i) {
+                if c == target_c {
+                    target_c.clone().white()
+                } else {
+                    target_c.clone().light_red()
+                }
             } else {
-                target_c.to_string().light_red()
+                target_c.clone().fg(Color::Indexed(244))
             }
-        } else {
-            target_c.to_string().fg(Color::Indexed(244))
-        }
-    }).collect()
+        );
+    }
+
+    if let Some(extra) = app.extra_text.get(&app.target_text.len()) {
+        chars.extend(extra.iter().map(|c| c.clone().red()));
+    }
+
+    chars
 }
 
 fn render_timer(app: &App, frame: &mut Frame) {
     if
-        std::env::args().find(|i| i == "-t").is_none()
+        app.timer.get_time_limit().is_none()
             && app.timer.get_elapsed() > Duration::from_secs(3)
     {
         return;
@@ -64,34 +139,501 @@ fn render_timer(app: &App, frame: &mut Frame) {
     }
 }
 
-fn render_stats(app: &App, frame: &mut Frame) {
+fn render_live_stats(app: &App, frame: &mut Frame) {
+    let Some(position) = app.get_live_stats_position() else { return };
+    let rect = app.get_rect();
+
+    let y = match position {
+        LiveStatsPosition::Top => rect.y.saturating_sub(2),
+        // one empty line under the last line of the text
+        LiveStatsPosition::Bottom => get_text_bottom(app) + 2,
+    };
+
+    if y >= frame.size().height {
+        return;
+    }
+
+    let (typed, total) = app.get_word_progress();
+
+    frame.render_widget(
+        Paragraph::new(
+            format!("{:.0} wpm  {:.0}%  {}/{}", app.get_wpm(), app.get_accuracy(), typed, total)
+                .fg(Color::Indexed(244))
+        ).alignment(Alignment::Right),
+        Rect { y, height: 1, ..rect }
+    )
+}
+
+/// the last line the text is drawn on
+fn get_text_bottom(app: &App) -> u16 {
+    if app.is_in_scroller_mode() {
+        return app.get_rect().y;
+    }
+
+    let (text, _) = app.get_display_text();
+    get_xy_wrapped(text.len(), &text, app.get_rect()).1
+}
+
+fn render_keyboard(app: &App, frame: &mut Frame) {
+    let Some(rect) = app.get_keyboard_rect() else { return };
+
+    let qwerty = Layout::qwerty();
+    let layout = app.layout.as_ref().unwrap_or(&qwerty);
+
+    let next_char = app.target_text
+        .get(app.curr_text.len())
+        .and_then(|c| strip_accents(c).chars().next());
+
+    frame.render_widget(
+        Keyboard { layout, next_char, wrong_char: app.get_wrong_key() },
+        rect
+    )
+}
+
+/// how many personal bests the home screen lists
+const HOME_BESTS: usize = 5;
+
+// presets and the latest personal bests, centered on the whole screen
+fn render_home(app: &App, frame: &mut Frame) {
+    let Some(home) = &app.home else { return };
+    let size = frame.size();
+
+    let mut lines = vec![Line::from("tt-rs".bold()), Line::default()];
+
+    for (name, presets) in PRESET_ROWS {
+        let mut spans = vec![Span::from(format!("{:>6}  ", name)).fg(Color::Indexed(244))];
+
+        for &preset in presets {
+            let text = format!(" {} ", preset);
+
+            spans.push(match preset == home.get_selected() {
+                true => text.black().on_white(),
+                false => text.into(),
+            });
+        }
+
+        lines.push(Line::from(spans));
+    }
+
+    let bests = app.history.get_bests();
+
+    if !bests.is_empty() {
+        lines.extend([Line::default(), Line::from("personal bests".fg(Color::Indexed(244)))]);
+
+        for best in bests.iter().take(HOME_BESTS) {
+            lines.push(Line::from(format!(
+                "{:<14}{:>4.0} wpm {:>4.0}%  {}",
+                best.test, best.wpm, best.accuracy, best.get_age()
+            )));
+        }
+    }
+
+    let width = lines.iter().map(Line::width).max().unwrap_or(0) as u16;
+    let height = lines.len() as u16;
+
+    frame.render_widget(
+        Paragraph::new(lines),
+        Rect {
+            x: size.width.saturating_sub(width) / 2,
+            y: size.height.saturating_sub(height) / 2,
+            width: width.min(size.width),
+            height: height.min(size.height),
+        }
+    )
+}
+
+fn get_palette_value(app: &App, item: PaletteItem) -> String {
+    match item {
+        PaletteItem::WordCount => app.text_source.word_count.to_string(),
+        PaletteItem::Time => match app.timer.get_time_limit() {
+            Some(limit) => format!("{}s", limit.as_secs()),
+            None => "off".to_string(),
+        },
+        PaletteItem::Source => match app.text_source.quotes {
+            true => "quotes".to_string(),
+            false => "words".to_string(),
+        },
+        PaletteItem::WordList => app.text_source.list.clone().unwrap_or("words".to_string()),
+        PaletteItem::Mode => match app.is_in_scroller_mode() {
+            true => "scroller".to_string(),
+            false => "wrapped".to_string(),
+        },
+        PaletteItem::ErrorMode => app.error_mode.to_string(),
+        _ => String::new(),
+    }
+}
+
+// over whatever is on screen, in the middle
+fn render_palette(app: &App, frame: &mut Frame) {
+    let Some(palette) = &app.palette else { return };
+    let size = frame.size();
+
+    let lines: Vec<Line> = PaletteItem::ALL.iter()
+        .map(|&item| {
+            let text = match item.is_action() {
+                true => format!(" {} ", item.name()),
+                false => format!(" {:<10}{} ", item.name(), get_palette_value(app, item)),
+            };
+
+            match item == palette.get_selected() {
+                true => Line::from(text.black().on_white()),
+                false => Line::from(text),
+            }
+        })
+        .collect();
+
+    let width = 36.min(size.width);
+    let height = (lines.len() as u16 + 2).min(size.height);
+    let rect = Rect {
+        x: (size.width - width) / 2,
+        y: (size.height - height) / 2,
+        width,
+        height,
+    };
+
+    frame.render_widget(Clear, rect);
+    frame.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" settings ")
+                .title(Title::from(" ←/→ change, ESC close ").position(Position::Bottom))
+        ),
+        rect
+    )
+}
+
+// the text stays hidden until the countdown is over so nobody gets a head start
+fn render_countdown(app: &App, frame: &mut Frame) {
+    if let Some(countdown) = &app.countdown {
+        frame.render_widget(
+            Paragraph::new(countdown.get_remaining().to_string().bold())
+                .alignment(Alignment::Center),
+            app.get_rect()
+        )
+    }
+}
+
+// the text is hidden so the test can't be read ahead while the time is frozen
+fn render_paused(app: &App, frame: &mut Frame) {
+    let rect = app.get_rect();
+
+    frame.render_widget(
+        Paragraph::new(vec![
+            Line::from("PAUSED".bold()),
+            Line::default(),
+            Line::from(format!("{}s", app.timer.get_time().as_secs())),
+            Line::default(),
+            Line::from("press any key to resume".fg(Color::Indexed(244))),
+        ]).alignment(Alignment::Center),
+        // the scroller is one line high
+        Rect { height: rect.height.max(5), ..rect }.intersection(frame.size())
+    )
+}
+
+fn render_start_hint(app: &App, frame: &mut Frame) {
+    let rect = app.get_rect();
+    let hint = get_key_hint(app, Action::Start, "to start").unwrap_or_default();
+
+    frame.render_widget(
+        Paragraph::new(hint.fg(Color::Indexed(244))),
+        Rect { y: rect.y.saturating_sub(2), ..rect }
+    )
+}
+
+fn render_lobby(app: &App, frame: &mut Frame) {
+    let Some(race) = &app.race else { return };
+
+    let players: Vec<String> = race.get_players()
+        .iter()
+        .map(|player| player.name.clone())
+        .collect();
+
+    let hint = match race.is_host() {
+        true => get_key_hint(app, Action::Start, "to start the race").unwrap_or_default(),
+        false => "waiting for the host to start the race".to_string(),
+    };
+
     frame.render_widget(
         Paragraph::new(
-            format!(
-                "WPM: {:.0}\n\nAccuracy: {:.2}\ncorrect: {}\nincorrect: {}\nwords: {}\n\nTime: {}s\n\n\n\n\n\n\n TAB / ALT + n for next test, ALT + r to retry test",
-                    app.get_wpm(),
-                    app.get_accuracy(),
-                    app.get_correct(),
-                    app.get_incorrect(),
-                    app.target_text.iter().filter(|&&c| c.is_whitespace()).count() + 1,
-                    app.timer.get_time().as_secs()
-            )).alignment(Alignment::Center),
+            format!("{}\n\nplayers:\n{}\n\n\n{}", race.get_location(), players.join("\n"), hint)
+        ).alignment(Alignment::Center),
         app.get_rect()
     )
 }
 
-fn render_wrapped(app: &App, frame: &mut Frame, chars: Vec<Span>) {
+// one progress bar per racer, stacked upwards from above the timer
+fn render_racers(app: &App, frame: &mut Frame) {
+    let Some(race) = &app.race else { return };
+    let rect = app.get_rect();
+
+    for (i, player) in race.get_players().iter().enumerate() {
+        let Some(y) = rect.y.checked_sub(3 + i as u16) else { break };
+
+        let color = match player.id == race.get_id() {
+            true => Color::White,
+            false => Color::Indexed(244),
+        };
+
+        frame.render_widget(
+            LineGauge::default()
+                .ratio(player.progress.clamp(0.0, 1.0))
+                .label(format!("{} {:.0}wpm ", player.name, player.wpm))
+                .gauge_style(Style::default().fg(color)),
+            Rect { y, height: 1, ..rect }
+        )
+    }
+}
+
+/// `"{keys} {what}"` with the keys bound to `action`, or nothing if it's unbound
+fn get_key_hint(app: &App, action: Action, what: &str) -> Option<String> {
+    app.keymap.get_keys(action).map(|keys| format!("{} {}", keys, what))
+}
+
+fn get_results_hint(app: &App) -> String {
+    match &app.race {
+        Some(race) if race.is_host() => get_key_hint(app, Action::Start, "to start the next race").unwrap_or_default(),
+        Some(_) => "waiting for the host to start the next race".to_string(),
+        None => [
+            get_key_hint(app, Action::NextTest, "for next test"),
+            get_key_hint(app, Action::Restart, "to retry test"),
+            get_key_hint(app, Action::Home, "for home"),
+            get_key_hint(app, Action::History, "for history"),
+            get_key_hint(app, Action::PracticeMissed, "to practice missed words").filter(|_| !app.get_missed_words().is_empty()),
+        ].into_iter().flatten().collect::<Vec<_>>().join(", "),
+    }
+}
+
+fn render_stats(app: &App, frame: &mut Frame) {
+    let hint = get_results_hint(app);
+
+    let previous_best = match &app.previous_best {
+        Some(best) => format!("\nprevious best: {:.0} ({:+.0})", best.wpm, app.get_wpm() - best.wpm),
+        None => String::new(),
+    };
+
+    let mut text = Text::from(
+        format!(
+            "WPM: {:.0}{}\n\nAccuracy: {:.2}\ncorrect: {}\nincorrect: {} ({} corrected, {} uncorrected)\nmissed: {}\nwords: {}{}\n\nTime: {}s\nmode: {}\n\n\n\n\n {}",
+            app.get_wpm(),
+            previous_best,
+            app.get_accuracy(),
+            app.get_correct(),
+            app.get_incorrect(),
+            app.get_corrected(),
+            app.get_uncorrected(),
+            app.get_missed(),
+            app.target_text.iter().filter(|c| *c == " ").count() + 1,
+            get_word_report(app),
+            app.timer.get_time().as_secs(),
+            match &app.layout {
+                Some(layout) => format!("{} on {}", app.error_mode, layout.name),
+                None => app.error_mode.to_string(),
+            },
+            hint
+        )
+    );
+
+    if app.is_new_best() {
+        text.lines.splice(0..0, [Line::from("NEW PERSONAL BEST".light_green().bold()), Line::default()]);
+    }
+
+    let flags = app.get_flags();
+    if !flags.is_empty() {
+        text.lines.splice(0..0, [Line::from(format!("flagged: {}", join_flags(&flags)).light_red()), Line::default()]);
+    }
+
     frame.render_widget(
-        Paragraph::new(Line::from(chars))
-            .wrap(Wrap::default()),
+        Paragraph::new(text).alignment(Alignment::Center),
         app.get_rect()
     );
+
+    render_buttons(app, frame);
+}
+
+/// how many of the slowest and most missed words the results list
+const REPORT_WORDS: usize = 3;
+
+// e.g: `slowest: through (38), which (45)` and `most missed: their (2), weird (1)`
+fn get_word_report(app: &App) -> String {
+    let mut report = String::new();
+
+    let mut speeds = app.get_word_speeds();
+    speeds.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    if !speeds.is_empty() {
+        let slowest: Vec<String> = speeds.iter()
+            .take(REPORT_WORDS)
+            .map(|(word, wpm)| format!("{} ({:.0})", word, wpm))
+            .collect();
+
+        report += &format!("\nslowest: {}", slowest.join(", "));
+    }
+
+    let errors = app.get_word_errors();
+
+    if !errors.is_empty() {
+        let most_missed: Vec<String> = errors.iter()
+            .take(REPORT_WORDS)
+            .map(|(word, count)| format!("{} ({})", word, count))
+            .collect();
+
+        report += &format!("\nmost missed: {}", most_missed.join(", "));
+    }
+
+    report
+}
+
+fn render_failed(app: &App, frame: &mut Frame) {
+    let Some(reason) = &app.failed else { return };
+
+    let hint = get_results_hint(app);
+
+    frame.render_widget(
+        Paragraph::new(vec![
+            Line::from("FAILED".light_red().bold()),
+            Line::default(),
+            Line::from(reason.to_string()),
+            Line::default(),
+            Line::from(format!("WPM: {:.0}", app.get_wpm())),
+            Line::from(format!("Accuracy: {:.2}", app.get_accuracy())),
+            Line::from(format!("Time: {}s", app.timer.get_time().as_secs())),
+            Line::default(),
+            Line::default(),
+            Line::default(),
+            Line::default(),
+            Line::from(format!(" {}", hint)),
+        ]).alignment(Alignment::Center),
+        app.get_rect()
+    );
+
+    render_buttons(app, frame);
+}
+
+fn render_buttons(app: &App, frame: &mut Frame) {
+    for (button, rect) in app.get_buttons() {
+        frame.render_widget(
+            Paragraph::new(format!(" {} ", button.name()).black().on_white()),
+            *rect
+        )
+    }
+}
+
+/// how many bigrams and trigrams the stats screen lists
+const SLOWEST_NGRAMS: usize = 10;
+
+// the slowest bigrams and trigrams side by side
+fn render_ngrams(app: &App, frame: &mut Frame) {
+    let size = frame.size();
+    let bigrams = app.ngrams.get_slowest(2, SLOWEST_NGRAMS);
+    let trigrams = app.ngrams.get_slowest(3, SLOWEST_NGRAMS);
+
+    let mut lines = vec![
+        Line::from("slowest transitions".bold()),
+        Line::default(),
+        Line::from(format!("{:<22}{}", "bigrams", "trigrams").fg(Color::Indexed(244))),
+    ];
+
+    if bigrams.is_empty() && trigrams.is_empty() {
+        lines.push(Line::from("not enough tests yet"));
+    }
+
+    for i in 0..bigrams.len().max(trigrams.len()) {
+        lines.push(Line::from(format!("{:<22}{}", get_ngram_cell(&bigrams, i), get_ngram_cell(&trigrams, i))));
+    }
+
+    let hint = get_key_hint(app, Action::NgramStats, "or ESC to close").unwrap_or_else(|| "ESC to close".to_string());
+    lines.extend([Line::default(), Line::from(format!("ms per key (times typed), {}", hint).fg(Color::Indexed(244)))]);
+
+    let width = lines.iter().map(Line::width).max().unwrap_or(0) as u16;
+    let height = lines.len() as u16;
+
+    frame.render_widget(
+        Paragraph::new(lines),
+        Rect {
+            x: size.width.saturating_sub(width) / 2,
+            y: size.height.saturating_sub(height) / 2,
+            width: width.min(size.width),
+            height: height.min(size.height),
+        }
+    )
+}
+
+// e.g: `th   182 ms (40)`, empty when the column is shorter
+fn get_ngram_cell(ngrams: &[(&str, Latency)], i: usize) -> String {
+    ngrams.get(i).map_or(String::new(), |(ngram, latency)| {
+        format!("{:<4}{:>4.0} ms ({})", ngram, latency.get_average(), latency.count)
+    })
+}
+
+// the latest first, flagged results in red
+fn render_history(app: &App, frame: &mut Frame) {
+    let Some(history_view) = &app.history_view else { return };
+    let size = frame.size();
+
+    let mut lines = vec![
+        Line::from("history".bold()),
+        Line::default(),
+        Line::from(format!("{:<24}{:>8}{:>7}  {:<12}{}", "test", "wpm", "acc", "finished", "flags").fg(Color::Indexed(244))),
+    ];
+
+    // the title, header and hint take 5 lines
+    let visible = size.height.saturating_sub(5) as usize;
+    let results = app.history.get_results();
+
+    if results.is_empty() {
+        lines.push(Line::from("no finished tests yet"));
+    }
+
+    for result in results.iter().rev().skip(history_view.get_scroll()).take(visible) {
+        let line = format!(
+            "{:<24}{:>4.0} wpm{:>6.0}%  {:<12}{}",
+            result.test, result.wpm, result.accuracy, result.get_age(), join_flags(&result.flags)
+        );
+
+        lines.push(match result.is_valid() {
+            true => Line::from(line),
+            false => Line::from(line.light_red()),
+        });
+    }
+
+    let hint = get_key_hint(app, Action::History, "or ESC to close").unwrap_or_else(|| "ESC to close".to_string());
+    lines.extend([Line::default(), Line::from(format!("scroll or ↑/↓ to see more, {}", hint).fg(Color::Indexed(244)))]);
+
+    let width = lines.iter().map(Line::width).max().unwrap_or(0) as u16;
+    let height = lines.len() as u16;
+
+    frame.render_widget(
+        Paragraph::new(lines),
+        Rect {
+            x: size.width.saturating_sub(width) / 2,
+            y: size.height.saturating_sub(height) / 2,
+            width: width.min(size.width),
+            height: height.min(size.height),
+        }
+    )
+}
+
+// wrapped by hand so the lines always match the cursor from get_xy_wrapped
+fn render_wrapped(app: &App, frame: &mut Frame, mut chars: Vec<Span>) {
+    let (text, _) = app.get_display_text();
+    let line_starts = get_line_starts(&text, app.get_rect().width as usize);
+
+    let mut lines: Vec<Line> = line_starts.iter().rev()
+        .map(|&start| Line::from(chars.split_off(start)))
+        .collect();
+    lines.reverse();
+
+    frame.render_widget(Paragraph::new(lines), app.get_rect());
 }
 
 fn render_scroller(app: &App, frame: &mut Frame, chars: Vec<Span>) {
+    let (text, cursor) = app.get_display_text();
+    let cursor_x: usize = text[..cursor].iter().map(|c| c.width()).sum();
+
     frame.render_widget(
         Paragraph::new(Line::from(chars))
-            .scroll((0, (app.curr_text.len() as u16).saturating_sub(app.get_rect().width / 2))),
+            .scroll((0, (cursor_x as u16).saturating_sub(app.get_rect().width / 2))),
         app.get_rect()
     );
 }
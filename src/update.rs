@@ -1,85 +1,270 @@
-use crossterm::event::{self, Event, KeyEvent, KeyCode, KeyEventKind, KeyModifiers};
-use crate::{app::App, tui::Tui};
+use crossterm::event::{Event, KeyEvent, KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::Rect;
+use std::time::Duration;
+use crate::app::{App, Button, ErrorMode};
+use crate::keymap::Action;
+use crate::history::{Flag, HistoryView};
+use crate::home::{Home, Preset};
+use crate::palette::{cycle, Palette, PaletteItem, TIME_LIMITS, WORD_COUNTS};
+use crate::util::{get_word_lists, to_graphemes};
 use anyhow::Result;
 
-impl App {
-    fn handle_exit(&mut self, key: &KeyEvent) {
-        if 
-            (key.modifiers == KeyModifiers::ALT && key.code == KeyCode::Char('q')) ||
-            (key.modifiers == KeyModifiers::CONTROL && key.code == KeyCode::Char('c')) ||
-            key.code == KeyCode::Esc
-        {
-            self.exit();
-        }
-    }
+/// results scrolled by page up and page down in the history
+const HISTORY_PAGE: usize = 10;
+/// in graphemes, an IME commit longer than this was pasted from somewhere
+const MAX_IME_COMMIT: usize = 16;
 
-    fn handle_mod_alt(&mut self, key: &KeyEvent) -> Result<()> {
-        match key.code {
-            KeyCode::Char('s') => {
+impl App {
+    fn handle_action(&mut self, action: Action) -> Result<()> {
+        match action {
+            Action::Exit => self.exit(),
+            Action::SwapMode => {
                 self.swap_mode();
 
                 if !self.is_in_scroller_mode() {
                     self.target_text.drain(0..self.get_rect().width as usize / 2);
                     self.curr_text.drain(0..self.get_rect().width as usize / 2);
+                    self.shift_text_indices(-(self.get_rect().width as isize / 2));
                 }
             },
-            KeyCode::Char('r') => self.restart_test()?,
-            KeyCode::Char('n') => self.next_test()?,
-            _ => ()
-        }
-
-        Ok(())
-    }
-
-    fn handle_mod_ctrl(&mut self, key: &KeyEvent) -> Result<()> {
-        match key.code {
-            KeyCode::Char('h') |
-            KeyCode::Char('w') |
-            KeyCode::Backspace =>  {
+            // racers would lose the countdown everyone else is waiting on
+            Action::Restart if self.race.is_none() => self.restart_test()?,
+            Action::ToggleLiveStats => self.toggle_live_stats(),
+            Action::ToggleKeyboard => self.toggle_keyboard(),
+            Action::NextTest if self.race.is_none() => self.next_test()?,
+            Action::Start if self.is_waiting_for_start() => self.start_countdown(),
+            Action::Palette if self.race.is_none() => self.open_palette(),
+            Action::Home if self.race.is_none() => {
+                self.restart_test()?;
+                self.home = Some(Home::default());
+            },
+            // not while typing, the timer would keep running
+            Action::History if self.race.is_none() && (self.is_test_over() || !self.timer.is_started()) => {
+                self.history_view = Some(HistoryView::default());
+            },
+            Action::NgramStats if self.race.is_none() && (self.is_test_over() || !self.timer.is_started()) => {
+                self.show_ngrams = true;
+            },
+            Action::PracticeMissed if self.race.is_none() && self.is_test_over() => self.practice_missed_words()?,
+            Action::Pause if self.race.is_none() => match self.timer.is_paused() {
+                true => self.timer.resume(),
+                false => self.timer.pause(),
+            },
+            Action::DeleteWord => {
                 if self.is_in_scroller_mode() && self.curr_text.len() as u16 <= self.get_rect().width / 2 {
                     return Ok(())
                 }
 
-                if self.is_finished_typing() || self.timer.is_out_of_time() {
+                if self.is_test_over() || self.timer.is_paused() {
                     return Ok(())
                 }
 
                 self.del_last_word()
             }
+            _ => ()
+        }
 
+        Ok(())
+    }
+
+    fn handle_home_key(&mut self, key: &KeyEvent, action: Option<Action>) -> Result<()> {
+        let Some(home) = &mut self.home else { return Ok(()) };
+
+        match key.code {
+            _ if action == Some(Action::Exit) => self.exit(),
+            _ if action == Some(Action::Palette) => self.start_preset(Preset::Custom)?,
+            _ if action == Some(Action::History) => self.history_view = Some(HistoryView::default()),
+            _ if action == Some(Action::NgramStats) => self.show_ngrams = true,
+            KeyCode::Up | KeyCode::Char('k') => home.select_up(),
+            KeyCode::Down | KeyCode::Char('j') => home.select_down(),
+            KeyCode::Left | KeyCode::Char('h') => home.select_left(),
+            KeyCode::Right | KeyCode::Char('l') => home.select_right(),
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                let preset = home.get_selected();
+                self.start_preset(preset)?
+            },
             _ => ()
         }
 
         Ok(())
     }
 
-    fn handle_mods(&mut self, key: &KeyEvent) -> Result<()> {
-        match key.modifiers {
-            KeyModifiers::ALT => self.handle_mod_alt(key),
-            KeyModifiers::CONTROL => self.handle_mod_ctrl(key),
-            _ => Ok(())
+    fn start_preset(&mut self, preset: Preset) -> Result<()> {
+        self.home = None;
+
+        match preset {
+            Preset::Time(secs) => {
+                self.timer.set_time_limit(Some(Duration::from_secs(secs)));
+                self.text_source.quotes = false;
+            },
+            Preset::Words(count) => {
+                self.timer.set_time_limit(None);
+                self.text_source.quotes = false;
+                self.text_source.word_count = count;
+            },
+            Preset::Quote(length) => {
+                self.timer.set_time_limit(None);
+                self.text_source.quotes = true;
+                self.text_source.quote_length = Some(length);
+            },
+            Preset::Custom => {
+                self.open_palette();
+                return Ok(())
+            },
         }
+
+        self.next_test()
     }
 
-    fn handle_char_input(&mut self, char: char) -> Result<()> {
-        if !self.timer.is_started() {
-            self.timer.start();
+    fn handle_history_key(&mut self, key: &KeyEvent, action: Option<Action>) -> Result<()> {
+        let len = self.history.get_results().len();
+        let Some(history_view) = &mut self.history_view else { return Ok(()) };
+
+        match key.code {
+            KeyCode::Esc => self.history_view = None,
+            _ if action == Some(Action::History) => self.history_view = None,
+            _ if action == Some(Action::Exit) => self.exit(),
+            KeyCode::Up | KeyCode::Char('k') => history_view.scroll_up(1),
+            KeyCode::Down | KeyCode::Char('j') => history_view.scroll_down(1, len),
+            KeyCode::PageUp => history_view.scroll_up(HISTORY_PAGE),
+            KeyCode::PageDown => history_view.scroll_down(HISTORY_PAGE, len),
+            _ => ()
         }
 
-        if self.is_finished_typing() || self.timer.is_out_of_time() {
+        Ok(())
+    }
+
+    fn handle_mouse(&mut self, mouse: &MouseEvent) -> Result<()> {
+        if self.palette.is_some() || self.home.is_some() || self.show_ngrams || self.is_in_race_lobby() {
             return Ok(())
-        } 
+        }
+
+        if let Some(history_view) = &mut self.history_view {
+            let len = self.history.get_results().len();
+
+            match mouse.kind {
+                MouseEventKind::ScrollUp => history_view.scroll_up(1),
+                MouseEventKind::ScrollDown => history_view.scroll_down(1, len),
+                _ => ()
+            }
 
-        if self.target_text.len() == self.curr_text.len() {
             return Ok(())
         }
 
-        if self.target_text[self.curr_text.len()] == ' ' {
+        if mouse.kind != MouseEventKind::Down(MouseButton::Left) {
             return Ok(())
         }
 
-        self.curr_text.push(char);
-        self.check_is_char_corr()?;
+        match self.get_button_at(mouse.column, mouse.row) {
+            Some(Button::Next) => self.handle_action(Action::NextTest),
+            Some(Button::Retry) => self.handle_action(Action::Restart),
+            Some(Button::PracticeMissed) => self.handle_action(Action::PracticeMissed),
+            Some(Button::History) => self.handle_action(Action::History),
+            None => self.restart_from(mouse.column, mouse.row),
+        }
+    }
+
+    /// the test is hidden behind the palette, so its timer waits for it to close
+    fn open_palette(&mut self) {
+        let mut palette = Palette::default();
+
+        if !self.timer.is_paused() {
+            self.timer.pause();
+            palette.paused_timer = self.timer.is_paused();
+        }
+
+        self.palette = Some(palette);
+    }
+
+    fn close_palette(&mut self) {
+        if self.palette.take().is_some_and(|palette| palette.paused_timer) {
+            self.timer.resume();
+        }
+    }
+
+    fn handle_palette_key(&mut self, key: &KeyEvent, action: Option<Action>) -> Result<()> {
+        let Some(palette) = &mut self.palette else { return Ok(()) };
+        let selected = palette.get_selected();
+
+        match key.code {
+            KeyCode::Esc => self.close_palette(),
+            _ if action == Some(Action::Palette) => self.close_palette(),
+            _ if action == Some(Action::Exit) => self.exit(),
+            KeyCode::Up | KeyCode::Char('k') => palette.select_prev(),
+            KeyCode::Down | KeyCode::Char('j') => palette.select_next(),
+            KeyCode::Left | KeyCode::Char('h') => self.apply_palette_item(selected, false)?,
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Enter | KeyCode::Char(' ') => {
+                self.apply_palette_item(selected, true)?
+            },
+            _ => ()
+        }
+
+        Ok(())
+    }
+
+    /// steps a setting to its next (or previous) value and starts a new test with it
+    fn apply_palette_item(&mut self, item: PaletteItem, forward: bool) -> Result<()> {
+        match item {
+            PaletteItem::WordCount => {
+                self.text_source.word_count = cycle(&WORD_COUNTS, &self.text_source.word_count, forward);
+            },
+            PaletteItem::Time => {
+                let curr = self.timer.get_time_limit().map(|limit| limit.as_secs());
+                let limit = cycle(&TIME_LIMITS, &curr, forward);
+
+                self.timer.set_time_limit(limit.map(Duration::from_secs));
+            },
+            PaletteItem::Source => self.text_source.quotes = !self.text_source.quotes,
+            PaletteItem::WordList => {
+                let lists: Vec<Option<String>> = std::iter::once(None)
+                    .chain(get_word_lists().into_iter().map(Some))
+                    .collect();
+
+                self.text_source.list = cycle(&lists, &self.text_source.list, forward);
+            },
+            PaletteItem::Mode => self.swap_mode(),
+            PaletteItem::ErrorMode => self.error_mode = cycle(&ErrorMode::ALL, &self.error_mode, forward),
+            PaletteItem::Restart => {
+                self.palette = None;
+                return self.restart_test();
+            },
+            PaletteItem::NextTest => self.palette = None,
+            PaletteItem::Exit => {
+                self.exit();
+                return Ok(());
+            },
+        }
+
+        self.next_test()
+    }
+
+    fn handle_char_input(&mut self, char: &str) -> Result<()> {
+        let char = &self.input.resolve(char, self.target_text.get(self.curr_text.len()));
+
+        if !self.timer.is_started() {
+            self.timer.start();
+        }
+
+        if self.is_test_over() {
+            return Ok(())
+        } 
+
+        if let Some(result) = self.try_combine_char(char) {
+            result?;
+        } else {
+            // anything but a combining mark settles the letter before it
+            if self.resolve_pending_letter()? {
+                return Ok(())
+            }
+
+            if self.target_text.get(self.curr_text.len()).is_none_or(|c| c == " ") {
+                // typing past the end of a word
+                return self.push_extra_char(char);
+            }
+
+            self.curr_text.push(char.clone());
+            self.check_is_char_corr()?;
+        }
 
         if self.is_finished_typing() && !self.timer.is_stopped() {
             self.timer.stop();
@@ -93,17 +278,21 @@ impl App {
             return;
         }
 
-        if self.is_finished_typing() || self.timer.is_out_of_time() {
+        if self.is_test_over() {
             return;
         }
 
-        if self.curr_text.get(self.curr_text.len().saturating_sub(1)) == Some(&' ') {
+        if self.pop_extra_char() {
+            return;
+        }
+
+        if self.curr_text.last().is_some_and(|c| c == " ") {
             self.del_whitespaces();
 
             return;
         }
 
-        self.curr_text.pop();
+        self.pop_letter();
     }
 
     fn handle_key_event(&mut self, key: &KeyEvent) -> Result<()> {
@@ -111,27 +300,149 @@ impl App {
             return Ok(())
         }
 
-        self.handle_exit(&key);
+        let action = self.keymap.get_action(key);
+
+        if self.palette.is_some() {
+            return self.handle_palette_key(key, action);
+        }
+
+        if self.show_ngrams {
+            match key.code {
+                KeyCode::Esc => self.show_ngrams = false,
+                _ if action == Some(Action::NgramStats) => self.show_ngrams = false,
+                _ if action == Some(Action::Exit) => self.exit(),
+                _ => ()
+            }
+
+            return Ok(())
+        }
+
+        if self.history_view.is_some() {
+            return self.handle_history_key(key, action);
+        }
+
+        if self.home.is_some() {
+            return self.handle_home_key(key, action);
+        }
+
+        if action == Some(Action::Exit) {
+            self.exit();
+            return Ok(())
+        }
+
+        if self.race.is_some() {
+            if action == Some(Action::Start) && (self.is_in_race_lobby() || self.is_test_over()) {
+                return self.start_race();
+            }
+
+            // there is nothing to type until the host sends a text
+            if self.is_in_race_lobby() {
+                return Ok(())
+            }
+        }
+
+        if let Some(action) = action {
+            return self.handle_action(action);
+        }
+
+        // the text is hidden while paused, so the key only resumes the test
+        if self.timer.is_paused() {
+            self.timer.resume();
+            return Ok(())
+        }
 
         if !key.modifiers.is_empty() && key.modifiers != KeyModifiers::SHIFT {
-            self.handle_mods(&key)?;
             return Ok(())
         }
 
+        // nothing can be typed before the test has started
+        if self.countdown.is_some() || self.is_waiting_for_start() {
+            return Ok(())
+        }
+
+        // pastes are left out, IME commits come all at once
+        let keystroke = matches!(key.code, KeyCode::Char(_) | KeyCode::Backspace) && !self.is_test_over();
+
         match key.code {
-            KeyCode::Tab => self.next_test()?,
-            KeyCode::Char(' ') => self.jump_to_next_word(),
-            KeyCode::Char(char) => self.handle_char_input(char)?,
+            KeyCode::Char(' ') if !self.input.is_composing() => self.jump_to_next_word()?,
+            KeyCode::Char(char) => {
+                let char = self.layout.as_ref().map_or(char, |layout| layout.remap(char));
+
+                if let Some(char) = self.input.compose(char) {
+                    self.handle_char_input(&char)?
+                }
+            },
             KeyCode::Backspace => self.handle_backspace(),
             _ => ()
         }
 
+        if keystroke {
+            self.log_keystroke();
+        }
+
+        Ok(())
+    }
+
+    // an IME commits the composed text as a paste
+    // pastes are rejected unless they come from an IME or --flag-paste is used
+    fn handle_paste(&mut self, text: &str) -> Result<()> {
+        if !self.input.is_paste_allowed() || self.is_in_race_lobby() || self.palette.is_some() || self.home.is_some() {
+            return Ok(())
+        }
+
+        if self.countdown.is_some() || self.is_waiting_for_start() || self.is_test_over() || self.timer.is_paused() {
+            return Ok(())
+        }
+
+        let chars = to_graphemes(text);
+
+        // an IME commits a word at most
+        let is_ime_commit = chars.len() <= MAX_IME_COMMIT && !text.trim_end().contains(char::is_whitespace);
+
+        if !self.input.is_ime_enabled() || !is_ime_commit {
+            self.flag(Flag::Pasted);
+        }
+
+        for char in chars {
+            match char.as_str() {
+                " " => self.jump_to_next_word()?,
+                _ => self.handle_char_input(&char)?,
+            }
+
+            self.log_keystroke();
+        }
+
         Ok(())
     }
 
-    pub fn update(&mut self, _tui: &mut Tui) -> Result<()> {
-        match event::read()? {
+    fn handle_focus_lost(&mut self) {
+        if !self.timer.is_started() || self.is_test_over() {
+            return;
+        }
+
+        self.flag(Flag::LostFocus);
+
+        // a race can't wait for one racer
+        if self.race.is_none() {
+            self.timer.pause();
+        }
+    }
+
+    fn handle_resize(&mut self, width: u16, height: u16) {
+        self.update_rect(Rect::new(0, 0, width, height));
+
+        if self.is_in_scroller_mode() && !self.is_test_over() {
+            self.adjust_filler_txt();
+        }
+    }
+
+    pub fn update(&mut self, event: Event) -> Result<()> {
+        match event {
             Event::Key(key) => self.handle_key_event(&key)?,
+            Event::Paste(text) => self.handle_paste(&text)?,
+            Event::Mouse(mouse) => self.handle_mouse(&mouse)?,
+            Event::Resize(width, height) => self.handle_resize(width, height),
+            Event::FocusLost => self.handle_focus_lost(),
             _ => ()
         }
 
@@ -1,91 +1,186 @@
-use std::{env, fs::File};
+use std::{env, fmt, fs::File, path::Path, str::FromStr};
 use anyhow::{Context, Result};
 use rand::{seq::SliceRandom, thread_rng, Rng};
-use ron::de::from_reader;
+use ron::{de::from_reader, ser::{to_string_pretty, PrettyConfig}};
+use serde::{de::DeserializeOwned, Serialize};
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// `$XDG_CONFIG_HOME/tt-rs/{name}` if it exists, `~/.config/tt-rs/{name}` otherwise
+pub fn get_config_path(name: &str) -> Result<String> {
+    if let Ok(xdg_conf_home) = env::var("XDG_CONFIG_HOME") {
+        let xdg_conf_path = format!("{}/tt-rs/{}", xdg_conf_home, name);
+
+        if std::fs::metadata(&xdg_conf_path).is_ok() {
+            return Ok(xdg_conf_path);
+        }
+    }
+
+    Ok(format!("{}/.config/tt-rs/{}", env::var("HOME")?, name))
+}
+
+/// the config file `name`, or the default if there is none yet,
+/// `what` names the file in errors (e.g: "history file")
+pub fn load_ron<T: DeserializeOwned + Default>(name: &str, what: &str) -> Result<T> {
+    let file_path = get_config_path(name)?;
+
+    if std::fs::metadata(&file_path).is_err() {
+        return Ok(T::default());
+    }
+
+    from_reader(
+        File::open(&file_path).with_context(|| format!("{} {} can't be opened", what, file_path))?
+    ).with_context(|| format!("{} {} is incorrect", what, file_path))
+}
 
-pub fn get_random_quotes() -> Result<Vec<char>> {
-    let file_path = {
-        let xdg_conf_path = format!("{}/tt-rs/quotes.ron", env::var("XDG_CONFIG_HOME")?);
+/// writes the config file `name`, creating ~/.config/tt-rs if needed
+pub fn save_ron<T: Serialize>(name: &str, what: &str, value: &T) -> Result<()> {
+    let file_path = get_config_path(name)?;
 
-        if std::fs::metadata(&xdg_conf_path).is_err() {
-            format!("{}/.config/tt-rs/quotes.ron", env::var("HOME")?)
-        } else {
-            xdg_conf_path
+    if let Some(dir) = Path::new(&file_path).parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    std::fs::write(&file_path, to_string_pretty(value, PrettyConfig::default())?)
+        .with_context(|| format!("{} {} can't be written", what, file_path))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum QuoteLength {
+    Short,
+    Long,
+}
+
+impl QuoteLength {
+    fn fits(&self, quote: &str) -> bool {
+        let len = quote.chars().count();
+
+        match self {
+            QuoteLength::Short => len < 150,
+            QuoteLength::Long => len >= 250,
         }
-    };
+    }
+}
+
+impl fmt::Display for QuoteLength {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            QuoteLength::Short => "short",
+            QuoteLength::Long => "long",
+        })
+    }
+}
+
+/// any quote if there is none of `length`
+pub fn get_random_quotes(length: Option<QuoteLength>) -> Result<Vec<String>> {
+    let file_path = get_config_path("quotes.ron")?;
 
     let conts: Vec<String> = from_reader(
         File::open(file_path).with_context(|| "quotes.ron(~/.config/tt-rs/quotes.ron) file is incorrect or missing")?
     )?;
 
-    let vec_conts: Vec<Vec<char>> = conts.iter()
-        .map(|s| s.chars().collect())
-        .collect();
+    let fitting: Vec<&String> = match length {
+        Some(length) => conts.iter().filter(|quote| length.fits(quote)).collect(),
+        None => Vec::new(),
+    };
+
+    if let Some(quote) = fitting.choose(&mut thread_rng()) {
+        return Ok(to_graphemes(quote));
+    }
 
     let random_idx = thread_rng().gen_range(0..conts.len());
-    Ok(vec_conts[random_idx].clone())
+    Ok(to_graphemes(&conts[random_idx]))
 }
 
-pub fn get_random_words(args: &Vec<String>) -> Result<Vec<char>> {
-    let file_path = {
-        let xdg_conf_path = format!("{}/tt-rs/words.ron", env::var("XDG_CONFIG_HOME")?);
-
-        if std::fs::metadata(&xdg_conf_path).is_err() {
-            format!("{}/.config/tt-rs/words.ron", env::var("HOME")?)
-        } else {
-            xdg_conf_path
-        }
+/// `list` is a file in ~/.config/tt-rs/lists, words.ron is used without one,
+/// lists shorter than `txt_len` are repeated, shuffled again each time
+pub fn get_random_words(txt_len: usize, list: Option<&str>) -> Result<Vec<String>> {
+    let file_name = match list {
+        // written by the history, outside of lists so it can't overwrite one
+        Some("trouble") => "trouble.ron".to_string(),
+        Some(list) => format!("lists/{}.ron", list),
+        None => "words.ron".to_string(),
     };
+    let file_path = get_config_path(&file_name)?;
 
-    let mut conts: Vec<String> = from_reader(
-        File::open(file_path).with_context(|| "words.ron(~/.config/tt-rs/words.ron) file is incorrect or missing")?
+    let conts: Vec<String> = from_reader(
+        File::open(file_path).with_context(|| format!("{}(~/.config/tt-rs/{}) file is incorrect or missing", file_name, file_name))?
     )?;
 
-    conts.shuffle(&mut thread_rng());
+    if conts.is_empty() {
+        anyhow::bail!("{}(~/.config/tt-rs/{}) has no words", file_name, file_name);
+    }
+
+    let mut words = Vec::with_capacity(txt_len);
 
-    let txt_len = {
-        if let Some(length) = args.iter().position(|i| i == &"-w".to_string()) {
-            args.get(length + 1)
-                .with_context(|| "add number after -w (e.g: -w 30)")?
-                .parse()
-                .with_context(|| "incorrect number: add number after -w (e.g: -w 30)")?
-        } else { 25 }
+    while words.len() < txt_len {
+        let mut round = conts.clone();
+        round.shuffle(&mut thread_rng());
+        words.extend(round.into_iter().take(txt_len - words.len()));
+    }
+
+    Ok(to_graphemes(&words.join(" ")))
+}
+
+/// names of the word lists in ~/.config/tt-rs/lists, and trouble once the history made it
+pub fn get_word_lists() -> Vec<String> {
+    let Ok(dir) = get_config_path("lists").and_then(|path| Ok(std::fs::read_dir(path)?)) else {
+        return Vec::new();
     };
 
-    let vec_conts: Vec<Vec<char>> = conts.iter()
-        .map(|s| s.chars().collect())
+    let mut lists: Vec<String> = dir
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            entry.file_name()
+                .to_str()?
+                .strip_suffix(".ron")
+                .map(String::from)
+        })
         .collect();
 
-    if txt_len > vec_conts.len() {
-        return Ok(vec_conts.join(&' '));
+    if get_config_path("trouble.ron").is_ok_and(|path| std::fs::metadata(path).is_ok()) {
+        lists.push("trouble".to_string());
     }
 
-    Ok(vec_conts[..txt_len].join(&' '))
+    lists.sort();
+    lists.dedup();
+    lists
 }
 
+/// splits into grapheme clusters, composed so `e` + `´` compares equal to `é`
+pub fn to_graphemes(str: &str) -> Vec<String> {
+    str.nfc()
+        .collect::<String>()
+        .graphemes(true)
+        .map(String::from)
+        .collect()
+}
 
-pub fn get_prev_whitespace(str: &Vec<char>, idx: usize) -> usize {
-    for i in (0..idx).rev() {
-        if let Some(char) = str.get(i) {
-            if *char == ' ' {
-                return i;
-            }
-        }
-    }
 
-    return 0;
+/// value after `flag`, `example` is shown in the error if it's missing or can't be parsed
+pub fn get_arg<T: FromStr>(args: &[String], flag: &str, example: &str) -> Result<Option<T>> {
+    let Some(idx) = args.iter().position(|i| i == flag) else {
+        return Ok(None);
+    };
+
+    args.get(idx + 1)
+        .with_context(|| format!("add value after {} (e.g: {} {})", flag, flag, example))?
+        .parse()
+        .ok()
+        .with_context(|| format!("incorrect value: add value after {} (e.g: {} {})", flag, flag, example))
+        .map(Some)
 }
 
-pub fn get_next_whitespace(str: &Vec<char>, idx: usize) -> usize {
-    for i in idx..str.len() {
+pub fn get_prev_whitespace(str: &[String], idx: usize) -> usize {
+    for i in (0..idx).rev() {
         if let Some(char) = str.get(i) {
-            if *char == ' ' {
+            if char == " " {
                 return i;
             }
         }
     }
 
-    return idx;
+    0
 }
 
 pub fn logg(str: String) {